@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE guest_carts (
+    id VARCHAR NOT NULL DEFAULT concat('guest-', uuid_generate_v4()) PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE guest_cart_items (
+    guest_cart_id VARCHAR NOT NULL,
+    product_id VARCHAR NOT NULL,
+    quantity INT NOT NULL,
+    PRIMARY KEY (guest_cart_id, product_id),
+    FOREIGN KEY (guest_cart_id) REFERENCES guest_carts(id) ON DELETE CASCADE,
+    FOREIGN KEY (product_id) REFERENCES products(id) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS set_updated_at ON carts;
+
+ALTER TABLE carts DROP COLUMN updated_at;
+ALTER TABLE carts DROP COLUMN created_at;
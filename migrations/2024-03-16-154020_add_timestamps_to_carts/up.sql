@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE carts ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE carts ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+SELECT diesel_manage_updated_at('carts');
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE abandoned_carts (
+    id VARCHAR NOT NULL DEFAULT concat('abandoned-', uuid_generate_v4()) PRIMARY KEY,
+    user_id VARCHAR NOT NULL REFERENCES users(id),
+    products JSONB NOT NULL,
+    item_count INTEGER NOT NULL,
+    value NUMERIC(10, 2) NOT NULL,
+    last_activity_at TIMESTAMP NOT NULL,
+    detected_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    recovery_sent_at TIMESTAMP,
+    recovered_at TIMESTAMP,
+    UNIQUE (user_id, last_activity_at)
+);
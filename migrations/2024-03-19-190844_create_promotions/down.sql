@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE orders DROP COLUMN discount;
+ALTER TABLE orders DROP COLUMN promotion_code;
+
+DROP TABLE IF EXISTS promotions;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE promotions (
+    id VARCHAR NOT NULL DEFAULT concat('promo-', uuid_generate_v4()) PRIMARY KEY,
+    code VARCHAR NOT NULL UNIQUE,
+    description VARCHAR,
+    kind VARCHAR NOT NULL CHECK (kind IN ('percent', 'fixed')),
+    amount NUMERIC(10, 2) NOT NULL CHECK (amount > 0),
+    usage_limit INTEGER,
+    times_redeemed INTEGER NOT NULL DEFAULT 0,
+    starts_at TIMESTAMP,
+    ends_at TIMESTAMP,
+    min_subtotal NUMERIC(10, 2),
+    categories TEXT[],
+    product_ids TEXT[],
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (kind = 'fixed' OR amount <= 100)
+);
+
+ALTER TABLE orders ADD COLUMN promotion_code VARCHAR;
+ALTER TABLE orders ADD COLUMN discount NUMERIC(10, 2);
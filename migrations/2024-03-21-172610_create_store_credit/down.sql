@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE orders DROP COLUMN store_credit;
+
+DROP TABLE IF EXISTS credit_ledger;
+DROP TABLE IF EXISTS gift_cards;
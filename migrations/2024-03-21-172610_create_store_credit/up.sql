@@ -0,0 +1,31 @@
+-- Your SQL goes here
+CREATE TABLE gift_cards (
+    id VARCHAR NOT NULL DEFAULT concat('giftcard-', uuid_generate_v4()) PRIMARY KEY,
+    code VARCHAR NOT NULL UNIQUE DEFAULT upper(substr(md5(random()::text), 1, 16)),
+    initial_balance NUMERIC(10, 2) NOT NULL CHECK (initial_balance > 0),
+    balance NUMERIC(10, 2) NOT NULL CHECK (balance >= 0),
+    issued_by VARCHAR NOT NULL,
+    note VARCHAR,
+    expires_at TIMESTAMP,
+    redeemed_by VARCHAR REFERENCES users(id) ON DELETE SET NULL,
+    redeemed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- every change to a user's store credit, the balance is the sum of the amounts
+CREATE TABLE credit_ledger (
+    id VARCHAR NOT NULL DEFAULT concat('credit-', uuid_generate_v4()) PRIMARY KEY,
+    user_id VARCHAR NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount NUMERIC(10, 2) NOT NULL CHECK (amount <> 0),
+    reason VARCHAR NOT NULL CHECK (reason IN ('gift_card', 'grant', 'checkout', 'checkout_released', 'refund')),
+    gift_card_id VARCHAR REFERENCES gift_cards(id) ON DELETE SET NULL,
+    order_id VARCHAR REFERENCES orders(id) ON DELETE SET NULL,
+    checkout_session_id VARCHAR,
+    note VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX credit_ledger_user_id_idx ON credit_ledger (user_id);
+CREATE INDEX credit_ledger_checkout_session_id_idx ON credit_ledger (checkout_session_id) WHERE checkout_session_id IS NOT NULL;
+
+ALTER TABLE orders ADD COLUMN store_credit NUMERIC(10, 2);
@@ -0,0 +1,32 @@
+-- Your SQL goes here
+CREATE TABLE shipping_zones (
+    id VARCHAR NOT NULL DEFAULT concat('zone-', uuid_generate_v4()) PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    countries TEXT[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- flat: always `amount`
+-- weight: `amount` plus `rate_per_kg` for every started kilogram of the package
+-- free_over: `amount`, free once the cart subtotal reaches `free_over`
+CREATE TABLE shipping_methods (
+    id VARCHAR NOT NULL DEFAULT concat('shipmethod-', uuid_generate_v4()) PRIMARY KEY,
+    zone_id VARCHAR NOT NULL REFERENCES shipping_zones(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL CHECK (kind IN ('flat', 'weight', 'free_over')),
+    amount NUMERIC(10, 2) NOT NULL CHECK (amount >= 0),
+    rate_per_kg NUMERIC(10, 2) CHECK (rate_per_kg >= 0),
+    free_over NUMERIC(10, 2) CHECK (free_over >= 0),
+    min_days INTEGER,
+    max_days INTEGER,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (kind <> 'weight' OR rate_per_kg IS NOT NULL),
+    CHECK (kind <> 'free_over' OR free_over IS NOT NULL)
+);
+
+CREATE INDEX shipping_methods_zone_id_idx ON shipping_methods (zone_id);
+
+ALTER TABLE orders ADD COLUMN shipping_method_id VARCHAR REFERENCES shipping_methods(id) ON DELETE SET NULL;
+ALTER TABLE orders ADD COLUMN shipping_method VARCHAR;
+ALTER TABLE orders ADD COLUMN shipping_cost NUMERIC(10, 2);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE products DROP COLUMN dimension_unit;
+ALTER TABLE products DROP COLUMN weight_unit;
+ALTER TABLE products DROP COLUMN height;
+ALTER TABLE products DROP COLUMN width;
+ALTER TABLE products DROP COLUMN length;
+ALTER TABLE products DROP COLUMN weight;
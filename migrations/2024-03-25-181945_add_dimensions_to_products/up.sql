@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE products ADD COLUMN weight NUMERIC(10, 3) CHECK (weight >= 0);
+ALTER TABLE products ADD COLUMN length NUMERIC(10, 2) CHECK (length >= 0);
+ALTER TABLE products ADD COLUMN width NUMERIC(10, 2) CHECK (width >= 0);
+ALTER TABLE products ADD COLUMN height NUMERIC(10, 2) CHECK (height >= 0);
+ALTER TABLE products ADD COLUMN weight_unit VARCHAR NOT NULL DEFAULT 'kg' CHECK (weight_unit IN ('kg', 'g', 'lb', 'oz'));
+ALTER TABLE products ADD COLUMN dimension_unit VARCHAR NOT NULL DEFAULT 'cm' CHECK (dimension_unit IN ('cm', 'in'));
@@ -0,0 +1,20 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE orders ADD COLUMN address VARCHAR NOT NULL DEFAULT '';
+
+UPDATE orders SET address =
+    coalesce(address_line1, '') || ', ' ||
+    coalesce(address_line2, '') || ', ' ||
+    coalesce(address_city, '') || ' ' ||
+    coalesce(address_state, '') || ' ' ||
+    coalesce(address_postal_code, '') || ' ' ||
+    coalesce(address_country, '');
+
+ALTER TABLE orders ALTER COLUMN address DROP DEFAULT;
+
+ALTER TABLE orders DROP COLUMN address_country;
+ALTER TABLE orders DROP COLUMN address_postal_code;
+ALTER TABLE orders DROP COLUMN address_state;
+ALTER TABLE orders DROP COLUMN address_city;
+ALTER TABLE orders DROP COLUMN address_line2;
+ALTER TABLE orders DROP COLUMN address_line1;
+ALTER TABLE orders DROP COLUMN phone;
@@ -0,0 +1,32 @@
+-- Your SQL goes here
+ALTER TABLE orders ADD COLUMN phone VARCHAR;
+ALTER TABLE orders ADD COLUMN address_line1 VARCHAR;
+ALTER TABLE orders ADD COLUMN address_line2 VARCHAR;
+ALTER TABLE orders ADD COLUMN address_city VARCHAR;
+ALTER TABLE orders ADD COLUMN address_state VARCHAR;
+ALTER TABLE orders ADD COLUMN address_postal_code VARCHAR;
+ALTER TABLE orders ADD COLUMN address_country VARCHAR;
+
+-- addresses were stored as "line1, line2, city state postal_code country"
+UPDATE orders SET
+    address_line1 = NULLIF(parsed.parts[1], ''),
+    address_line2 = NULLIF(parsed.parts[2], ''),
+    address_city = NULLIF(parsed.tail[1], ''),
+    address_state = NULLIF(parsed.tail[2], ''),
+    address_postal_code = NULLIF(parsed.tail[3], ''),
+    address_country = NULLIF(parsed.tail[4], '')
+FROM (
+    SELECT
+        id,
+        string_to_array(address, ', ') AS parts,
+        regexp_match(split_part(address, ', ', 3), '^(.*) (\S*) (\S*) (\S*)$') AS tail
+    FROM orders
+) AS parsed
+WHERE orders.id = parsed.id
+    AND array_length(parsed.parts, 1) = 3
+    AND parsed.tail IS NOT NULL;
+
+-- anything else is kept whole so it can be corrected by hand
+UPDATE orders SET address_line1 = address WHERE address_line1 IS NULL AND address <> '';
+
+ALTER TABLE orders DROP COLUMN address;
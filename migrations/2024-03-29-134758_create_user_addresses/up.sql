@@ -0,0 +1,25 @@
+-- Your SQL goes here
+CREATE TABLE user_addresses (
+    id VARCHAR NOT NULL DEFAULT concat('address-', uuid_generate_v4()) PRIMARY KEY,
+    user_id VARCHAR NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    label VARCHAR,
+    name VARCHAR NOT NULL,
+    phone VARCHAR,
+    line1 VARCHAR NOT NULL,
+    line2 VARCHAR,
+    city VARCHAR NOT NULL,
+    state VARCHAR,
+    postal_code VARCHAR NOT NULL,
+    country VARCHAR NOT NULL,
+    is_default_shipping BOOLEAN NOT NULL DEFAULT false,
+    is_default_billing BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX user_addresses_user_id_idx ON user_addresses (user_id);
+-- a user has at most one default of each kind
+CREATE UNIQUE INDEX user_addresses_default_shipping_idx ON user_addresses (user_id) WHERE is_default_shipping;
+CREATE UNIQUE INDEX user_addresses_default_billing_idx ON user_addresses (user_id) WHERE is_default_billing;
+
+SELECT diesel_manage_updated_at('user_addresses');
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+UPDATE orders SET status = 'processing' WHERE status = 'partially_shipped';
+
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check CHECK (status IN ('processing', 'shipped', 'delievered', 'canceled', 'returned'));
+
+DROP TABLE shipments;
\ No newline at end of file
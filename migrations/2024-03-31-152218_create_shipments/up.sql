@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE shipments (
+    id VARCHAR NOT NULL DEFAULT concat('shipment-', uuid_generate_v4()) PRIMARY KEY,
+    order_id VARCHAR NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    -- the products in this package and how many of each, shaped like orders.products
+    products JSONB NOT NULL,
+    carrier VARCHAR NOT NULL,
+    tracking_number VARCHAR,
+    tracking_url VARCHAR,
+    shipped_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_by VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX shipments_order_id_idx ON shipments (order_id);
+
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check CHECK (status IN ('processing', 'partially_shipped', 'shipped', 'delievered', 'canceled', 'returned'));
\ No newline at end of file
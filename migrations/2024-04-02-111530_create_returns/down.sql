@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE returns;
+
+ALTER TABLE orders DROP COLUMN refunded_amount;
+ALTER TABLE orders DROP COLUMN payment_intent_id;
\ No newline at end of file
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+ALTER TABLE orders ADD COLUMN payment_intent_id VARCHAR;
+ALTER TABLE orders ADD COLUMN refunded_amount NUMERIC(10, 2) NOT NULL DEFAULT 0;
+
+CREATE TABLE returns (
+    id VARCHAR NOT NULL DEFAULT concat('return-', uuid_generate_v4()) PRIMARY KEY,
+    order_id VARCHAR NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    user_id VARCHAR NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- the products sent back and how many of each, shaped like orders.products
+    products JSONB NOT NULL,
+    reason VARCHAR,
+    status VARCHAR NOT NULL DEFAULT 'requested' CHECK (status IN ('requested', 'approved', 'rejected', 'received', 'refunded')),
+    note VARCHAR,
+    refund_amount NUMERIC(10, 2),
+    stripe_refund_id VARCHAR,
+    approved_at TIMESTAMP,
+    received_at TIMESTAMP,
+    refunded_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX returns_order_id_idx ON returns (order_id);
+CREATE INDEX returns_user_id_idx ON returns (user_id);
+
+SELECT diesel_manage_updated_at('returns');
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE orders DROP COLUMN canceled_at;
+ALTER TABLE orders DROP COLUMN canceled_by;
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE orders ADD COLUMN canceled_by VARCHAR;
+ALTER TABLE orders ADD COLUMN canceled_at TIMESTAMP;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users DROP COLUMN last_seen_at;
+ALTER TABLE users DROP COLUMN created_at;
+ALTER TABLE users DROP COLUMN marketing_opt_in;
+ALTER TABLE users DROP COLUMN phone;
+ALTER TABLE users DROP COLUMN display_name;
\ No newline at end of file
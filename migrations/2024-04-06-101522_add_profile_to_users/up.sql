@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN display_name VARCHAR;
+ALTER TABLE users ADD COLUMN phone VARCHAR;
+ALTER TABLE users ADD COLUMN marketing_opt_in BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE users ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE users ADD COLUMN last_seen_at TIMESTAMP;
\ No newline at end of file
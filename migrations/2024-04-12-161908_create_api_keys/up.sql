@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE api_keys (
+    id VARCHAR NOT NULL DEFAULT concat('apikey-', uuid_generate_v4()) PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    -- the start of the key, enough to recognise it in a list
+    prefix VARCHAR NOT NULL,
+    -- sha-256 of the key, the key itself is only shown once when it is created
+    key_hash VARCHAR NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_by VARCHAR NOT NULL,
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
\ No newline at end of file
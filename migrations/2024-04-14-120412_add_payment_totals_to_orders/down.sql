@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+UPDATE returns SET status = 'received', refund_amount = NULL WHERE status = 'refunding';
+ALTER TABLE returns DROP CONSTRAINT returns_status_check;
+ALTER TABLE returns ADD CONSTRAINT returns_status_check
+    CHECK (status IN ('requested', 'approved', 'rejected', 'received', 'refunded'));
+
+ALTER TABLE orders DROP COLUMN amount_paid;
+ALTER TABLE orders DROP COLUMN prices;
\ No newline at end of file
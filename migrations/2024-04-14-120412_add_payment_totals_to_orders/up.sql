@@ -0,0 +1,22 @@
+-- Your SQL goes here
+-- the unit price of each product when the order was placed and what stripe charged for it, refunds are worked out from these
+ALTER TABLE orders ADD COLUMN prices JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE orders ADD COLUMN amount_paid NUMERIC(10, 2) NOT NULL DEFAULT 0;
+
+-- orders placed before this only have the current prices to go by
+UPDATE orders SET prices = coalesce((
+    SELECT jsonb_object_agg(products.id, products.price::text)
+    FROM products
+    WHERE orders.products ? products.id AND products.price IS NOT NULL
+), '{}');
+
+UPDATE orders SET amount_paid = greatest(0, coalesce((
+    SELECT sum((orders.prices ->> line.key)::numeric * (line.value #>> '{}')::numeric)
+    FROM jsonb_each(orders.products) AS line
+), 0) - coalesce(discount, 0) - coalesce(store_credit, 0) + coalesce(shipping_cost, 0))
+WHERE payment_intent_id IS NOT NULL;
+
+-- a return is refunding while its stripe refund is in flight, a second refund of it is turned away
+ALTER TABLE returns DROP CONSTRAINT returns_status_check;
+ALTER TABLE returns ADD CONSTRAINT returns_status_check
+    CHECK (status IN ('requested', 'approved', 'rejected', 'received', 'refunding', 'refunded'));
\ No newline at end of file
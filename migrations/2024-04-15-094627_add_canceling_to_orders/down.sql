@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+UPDATE orders SET status = 'processing' WHERE status = 'canceling';
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check CHECK (status IN ('processing', 'partially_shipped', 'shipped', 'delievered', 'canceled', 'returned'));
\ No newline at end of file
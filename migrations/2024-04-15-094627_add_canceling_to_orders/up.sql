@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- an order is canceling while the refund of its payment is in flight, it can't ship and a failed cancel can be retried
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check CHECK (status IN ('processing', 'partially_shipped', 'shipped', 'delievered', 'canceling', 'canceled', 'returned'));
\ No newline at end of file
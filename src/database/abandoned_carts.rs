@@ -0,0 +1,71 @@
+use diesel::dsl::now;
+use diesel::result::Error;
+use diesel::{ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::abandoned_cart::{AbandonedCart, NewAbandonedCart};
+use crate::schema::abandoned_carts::dsl::*;
+
+// returns None when this idle period of the cart was already recorded
+pub(crate) fn db_record_abandoned_cart(
+    conn: &mut PgConnection,
+    new_abandoned_cart: NewAbandonedCart,
+) -> Result<Option<AbandonedCart>, Error> {
+    let abandoned_cart = diesel::insert_into(abandoned_carts)
+        .values(&new_abandoned_cart)
+        .on_conflict_do_nothing()
+        .get_result::<AbandonedCart>(conn)
+        .optional()?;
+
+    Ok(abandoned_cart)
+}
+
+pub(crate) fn db_get_abandoned_carts(
+    conn: &mut PgConnection,
+    since: Option<chrono::NaiveDateTime>,
+) -> Result<Vec<AbandonedCart>, Error> {
+    let mut query = abandoned_carts
+        .order(detected_at.desc())
+        .into_boxed();
+
+    if let Some(since) = since {
+        query = query.filter(detected_at.ge(since));
+    }
+
+    let all_abandoned_carts = query.load::<AbandonedCart>(conn)?;
+
+    Ok(all_abandoned_carts)
+}
+
+pub(crate) fn db_get_abandoned_cart(
+    conn: &mut PgConnection,
+    abandoned_cart_id: String,
+) -> Result<Option<AbandonedCart>, Error> {
+    let abandoned_cart = abandoned_carts
+        .find(abandoned_cart_id)
+        .first::<AbandonedCart>(conn)
+        .optional()?;
+
+    Ok(abandoned_cart)
+}
+
+pub(crate) fn db_mark_recovery_sent(
+    conn: &mut PgConnection,
+    abandoned_cart_id: String,
+) -> Result<AbandonedCart, Error> {
+    let abandoned_cart = diesel::update(abandoned_carts.find(abandoned_cart_id))
+        .set(recovery_sent_at.eq(now))
+        .get_result::<AbandonedCart>(conn)?;
+
+    Ok(abandoned_cart)
+}
+
+pub(crate) fn db_mark_recovered(
+    conn: &mut PgConnection,
+    abandoned_cart_id: String,
+) -> Result<AbandonedCart, Error> {
+    let abandoned_cart = diesel::update(abandoned_carts.find(abandoned_cart_id))
+        .set(recovered_at.eq(now))
+        .get_result::<AbandonedCart>(conn)?;
+
+    Ok(abandoned_cart)
+}
@@ -0,0 +1,126 @@
+use diesel::result::Error;
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::address::{NewUserAddress, UserAddress};
+use crate::schema::user_addresses::dsl::*;
+
+pub(crate) fn db_get_user_addresses(
+    conn: &mut PgConnection,
+    user: String,
+) -> Result<Vec<UserAddress>, Error> {
+    let addresses = user_addresses
+        .filter(user_id.eq(user))
+        .order(created_at.asc())
+        .load::<UserAddress>(conn)?;
+
+    Ok(addresses)
+}
+
+pub(crate) fn db_get_user_address(
+    conn: &mut PgConnection,
+    user: String,
+    address_id: String,
+) -> Result<Option<UserAddress>, Error> {
+    let address = user_addresses
+        .find(address_id)
+        .filter(user_id.eq(user))
+        .first::<UserAddress>(conn)
+        .optional()?;
+
+    Ok(address)
+}
+
+pub(crate) fn db_get_default_shipping_address(
+    conn: &mut PgConnection,
+    user: String,
+) -> Result<Option<UserAddress>, Error> {
+    let address = user_addresses
+        .filter(user_id.eq(user))
+        .filter(is_default_shipping.eq(true))
+        .first::<UserAddress>(conn)
+        .optional()?;
+
+    Ok(address)
+}
+
+pub(crate) fn db_create_user_address(
+    conn: &mut PgConnection,
+    new_address: NewUserAddress,
+    default_shipping: Option<bool>,
+    default_billing: Option<bool>,
+) -> Result<UserAddress, Error> {
+    conn.transaction(|conn| {
+        let first = db_get_user_addresses(conn, new_address.user_id.clone())?.is_empty();
+
+        let address = diesel::insert_into(user_addresses)
+            .values(&new_address)
+            .get_result::<UserAddress>(conn)?;
+
+        set_defaults(
+            conn,
+            &address,
+            default_shipping.or(Some(true).filter(|_| first)),
+            default_billing.or(Some(true).filter(|_| first)),
+        )
+    })
+}
+
+pub(crate) fn db_update_user_address(
+    conn: &mut PgConnection,
+    address_id: String,
+    new_address: NewUserAddress,
+    default_shipping: Option<bool>,
+    default_billing: Option<bool>,
+) -> Result<Option<UserAddress>, Error> {
+    conn.transaction(|conn| {
+        let address = diesel::update(user_addresses.find(address_id).filter(user_id.eq(new_address.user_id.clone())))
+            .set(&new_address)
+            .get_result::<UserAddress>(conn)
+            .optional()?;
+
+        match address {
+            Some(address) => set_defaults(conn, &address, default_shipping, default_billing).map(Some),
+            None => Ok(None),
+        }
+    })
+}
+
+pub(crate) fn db_delete_user_address(
+    conn: &mut PgConnection,
+    user: String,
+    address_id: String,
+) -> Result<usize, Error> {
+    let deleted_address = diesel::delete(user_addresses.find(address_id).filter(user_id.eq(user)))
+        .execute(conn)?;
+
+    Ok(deleted_address)
+}
+
+// making an address the default takes the flag away from the user's other addresses
+fn set_defaults(
+    conn: &mut PgConnection,
+    address: &UserAddress,
+    default_shipping: Option<bool>,
+    default_billing: Option<bool>,
+) -> Result<UserAddress, Error> {
+    if default_shipping == Some(true) {
+        diesel::update(user_addresses.filter(user_id.eq(address.user_id.clone()).and(id.ne(address.id.clone()))))
+            .set(is_default_shipping.eq(false))
+            .execute(conn)?;
+    }
+
+    if default_billing == Some(true) {
+        diesel::update(user_addresses.filter(user_id.eq(address.user_id.clone()).and(id.ne(address.id.clone()))))
+            .set(is_default_billing.eq(false))
+            .execute(conn)?;
+    }
+
+    let address = diesel::update(user_addresses.find(address.id.clone()))
+        .set((
+            is_default_shipping.eq(default_shipping.unwrap_or(address.is_default_shipping)),
+            is_default_billing.eq(default_billing.unwrap_or(address.is_default_billing)),
+        ))
+        .get_result::<UserAddress>(conn)?;
+
+    Ok(address)
+}
\ No newline at end of file
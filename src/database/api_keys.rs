@@ -0,0 +1,73 @@
+use diesel::dsl::{now, IntervalDsl};
+use diesel::result::Error;
+use diesel::{BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::api_key::{ApiKey, NewApiKey};
+use crate::schema::api_keys::dsl::*;
+
+// every column but the hash, which never leaves the database
+const API_KEY_COLUMNS: (id, name, prefix, scopes, created_by, expires_at, last_used_at, revoked_at, created_at) =
+    (id, name, prefix, scopes, created_by, expires_at, last_used_at, revoked_at, created_at);
+
+pub(crate) fn db_get_api_keys(
+    conn: &mut PgConnection,
+) -> Result<Vec<ApiKey>, Error> {
+    let keys = api_keys
+        .select(API_KEY_COLUMNS)
+        .order(created_at.desc())
+        .load::<ApiKey>(conn)?;
+
+    Ok(keys)
+}
+
+pub(crate) fn db_create_api_key(
+    conn: &mut PgConnection,
+    new_api_key: NewApiKey,
+) -> Result<ApiKey, Error> {
+    let api_key = diesel::insert_into(api_keys)
+        .values(&new_api_key)
+        .returning(API_KEY_COLUMNS)
+        .get_result::<ApiKey>(conn)?;
+
+    Ok(api_key)
+}
+
+// revoking twice keeps the first revocation time
+pub(crate) fn db_revoke_api_key(
+    conn: &mut PgConnection,
+    api_key_id: String,
+) -> Result<Option<ApiKey>, Error> {
+    diesel::update(api_keys.find(&api_key_id))
+        .filter(revoked_at.is_null())
+        .set(revoked_at.eq(now))
+        .execute(conn)?;
+
+    api_keys.find(api_key_id)
+        .select(API_KEY_COLUMNS)
+        .first::<ApiKey>(conn)
+        .optional()
+}
+
+/// the key with this hash when it is neither revoked nor expired, its last use is
+/// recorded at most once a minute so busy integrations don't write on every request
+pub(crate) fn db_use_api_key(
+    conn: &mut PgConnection,
+    hash: String,
+) -> Result<Option<ApiKey>, Error> {
+    let api_key = api_keys
+        .filter(key_hash.eq(hash))
+        .filter(revoked_at.is_null())
+        .filter(expires_at.is_null().or(expires_at.gt(now)))
+        .select(API_KEY_COLUMNS)
+        .first::<ApiKey>(conn)
+        .optional()?;
+
+    if let Some(api_key) = &api_key {
+        diesel::update(api_keys.find(&api_key.id))
+            .filter(last_used_at.is_null().or(last_used_at.lt((now - 1.minute()).nullable())))
+            .set(last_used_at.eq(now))
+            .execute(conn)?;
+    }
+
+    Ok(api_key)
+}
\ No newline at end of file
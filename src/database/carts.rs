@@ -1,8 +1,13 @@
+use diesel::dsl::{self, now, IntervalDsl};
 use diesel::result::Error;
-use diesel::{BoolExpressionMethods, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use diesel::{BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
 
-use crate::models::cart::{CartItem, NewCartItem};
+use crate::models::cart::{max_line_quantity, CartItem, CartLineError, NewCartItem};
+use crate::models::product::Product;
 use crate::schema::carts::dsl::*;
+use crate::schema::products;
+
+use super::products::db_get_multiple_products_by_id;
 
 pub(crate) fn db_get_cart_items_by_user_id (
     conn: &mut PgConnection,
@@ -15,6 +20,20 @@ pub(crate) fn db_get_cart_items_by_user_id (
     Ok(Some(cart_items))
 }
 
+// every line of the user's cart together with the product it refers to
+pub(crate) fn db_get_cart_with_products (
+    conn: &mut PgConnection,
+    input_id: String,
+) -> Result<Vec<(CartItem, Product)>, Error> {
+    let cart_with_products = carts
+        .inner_join(products::table)
+        .filter(user_id.eq(input_id))
+        .order(products::name.asc())
+        .load::<(CartItem, Product)>(conn)?;
+
+    Ok(cart_with_products)
+}
+
 pub(crate) fn db_create_cart_item (
     conn: &mut PgConnection,
     new_cart_item: NewCartItem,
@@ -79,4 +98,82 @@ pub(crate) fn db_delete_cart_items_by_user (
         .execute(conn)?;
 
     Ok(deleted_cart_items)
+}
+
+// check each (product_id, quantity) line against the products table and collect the lines that are not allowed
+pub(crate) fn db_validate_cart_lines (
+    conn: &mut PgConnection,
+    lines: Vec<(String, i32)>,
+) -> Result<Vec<CartLineError>, Error> {
+    let line_products = db_get_multiple_products_by_id(
+        conn,
+        lines.iter().map(|(line_product_id, _)| line_product_id.clone()).collect(),
+    )?;
+
+    let line_errors = lines.iter()
+        .filter_map(|(line_product_id, line_quantity)| {
+            let product = line_products.iter().find(|product| &product.id == line_product_id);
+            CartLineError::check(line_product_id, *line_quantity, product)
+        })
+        .collect::<Vec<CartLineError>>();
+
+    Ok(line_errors)
+}
+
+// add (product_id, quantity) lines to a user's cart, summing quantities of lines already in the cart and capping
+// them at the stock and max line quantity, lines for unknown or inactive products are dropped
+pub(crate) fn db_merge_into_cart (
+    conn: &mut PgConnection,
+    user: String,
+    lines: Vec<(String, i32)>,
+) -> Result<Option<Vec<CartItem>>, Error> {
+    let current_cart = db_get_cart_items_by_user_id(conn, user.clone())?.unwrap_or_default();
+
+    let line_products = db_get_multiple_products_by_id(
+        conn,
+        lines.iter().map(|(line_product_id, _)| line_product_id.clone()).collect(),
+    )?;
+
+    for (line_product_id, line_quantity) in lines {
+        let product = match line_products.iter().find(|product| product.id == line_product_id) {
+            Some(product) if product.active => product,
+            _ => continue,
+        };
+
+        let cap = product.inventory.unwrap_or(0).min(max_line_quantity());
+        let current_quantity = current_cart.iter()
+            .find(|item| item.product_id == line_product_id)
+            .map(|item| item.quantity);
+        let merged_quantity = (current_quantity.unwrap_or(0) + line_quantity).min(cap);
+
+        match current_quantity {
+            Some(current_quantity) if current_quantity != merged_quantity && merged_quantity > 0 => {
+                db_update_cart_item(conn, user.clone(), line_product_id, merged_quantity)?;
+            }
+            None if merged_quantity > 0 => {
+                db_create_cart_item(conn, NewCartItem {
+                    user_id: user.clone(),
+                    product_id: line_product_id,
+                    quantity: merged_quantity,
+                })?;
+            }
+            _ => continue,
+        }
+    }
+
+    db_get_cart_items_by_user_id(conn, user)
+}
+
+// users whose cart has not been touched for `idle_hours`, with the time it was last changed
+pub(crate) fn db_get_idle_carts (
+    conn: &mut PgConnection,
+    idle_hours: i32,
+) -> Result<Vec<(String, Option<chrono::NaiveDateTime>)>, Error> {
+    let idle_carts = carts
+        .group_by(user_id)
+        .select((user_id, dsl::max(updated_at)))
+        .having(dsl::max(updated_at).lt((now - idle_hours.hours()).nullable()))
+        .load::<(String, Option<chrono::NaiveDateTime>)>(conn)?;
+
+    Ok(idle_carts)
 }
\ No newline at end of file
@@ -0,0 +1,272 @@
+use bigdecimal::BigDecimal;
+use diesel::dsl::now;
+use diesel::result::Error;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::credit::{CreditEntry, CreditRefundError, GiftCard, GiftCardError, NewCreditEntry, NewGiftCard};
+use crate::models::order::Order;
+use crate::schema::{credit_ledger, gift_cards, orders, users};
+
+use super::returns::db_get_refunds_in_flight;
+
+pub(crate) fn db_issue_gift_card(
+    conn: &mut PgConnection,
+    new_gift_card: NewGiftCard,
+) -> Result<GiftCard, Error> {
+    let gift_card = diesel::insert_into(gift_cards::table)
+        .values(&new_gift_card)
+        .get_result::<GiftCard>(conn)?;
+
+    Ok(gift_card)
+}
+
+pub(crate) fn db_get_gift_cards(
+    conn: &mut PgConnection,
+) -> Result<Vec<GiftCard>, Error> {
+    let all_gift_cards = gift_cards::table
+        .order(gift_cards::created_at.desc())
+        .load::<GiftCard>(conn)?;
+
+    Ok(all_gift_cards)
+}
+
+// move the whole balance of a gift card into the user's store credit
+pub(crate) fn db_redeem_gift_card(
+    conn: &mut PgConnection,
+    code: String,
+    user: String,
+) -> Result<Result<CreditEntry, GiftCardError>, Error> {
+    let code = code.trim().to_uppercase();
+
+    conn.transaction(|conn| {
+        let gift_card = gift_cards::table
+            .filter(gift_cards::code.eq(code.clone()))
+            .for_update()
+            .first::<GiftCard>(conn)
+            .optional()?;
+
+        let gift_card = match gift_card {
+            Some(gift_card) => gift_card,
+            None => return Ok(Err(GiftCardError::NotFound { code })),
+        };
+
+        if gift_card.redeemed_at.is_some() {
+            return Ok(Err(GiftCardError::AlreadyRedeemed { code }));
+        }
+
+        if let Some(expires_at) = gift_card.expires_at {
+            if expires_at < chrono::Local::now().naive_local() {
+                return Ok(Err(GiftCardError::Expired { code, expires_at }));
+            }
+        }
+
+        diesel::update(gift_cards::table.find(gift_card.id.clone()))
+            .set((
+                gift_cards::balance.eq(BigDecimal::from(0)),
+                gift_cards::redeemed_by.eq(user.clone()),
+                gift_cards::redeemed_at.eq(now),
+            ))
+            .execute(conn)?;
+
+        db_add_credit(conn, NewCreditEntry {
+            user_id: user,
+            amount: gift_card.balance,
+            reason: "gift_card".to_string(),
+            gift_card_id: Some(gift_card.id),
+            ..Default::default()
+        })
+        .map(Ok)
+    })
+}
+
+pub(crate) fn db_add_credit(
+    conn: &mut PgConnection,
+    new_entry: NewCreditEntry,
+) -> Result<CreditEntry, Error> {
+    let entry = diesel::insert_into(credit_ledger::table)
+        .values(&new_entry)
+        .get_result::<CreditEntry>(conn)?;
+
+    Ok(entry)
+}
+
+// refund part of an order as store credit, for at most what is left to refund on it. it counts in the order's
+// refunded amount like a refund to the card
+pub(crate) fn db_refund_order_to_credit(
+    conn: &mut PgConnection,
+    order_id: String,
+    amount: BigDecimal,
+    note: Option<String>,
+) -> Result<Option<Result<CreditEntry, CreditRefundError>>, Error> {
+    conn.transaction(|conn| {
+        let order = orders::table
+            .find(order_id)
+            .for_update()
+            .first::<Order>(conn)
+            .optional()?;
+
+        let order = match order {
+            Some(order) => order,
+            None => return Ok(None),
+        };
+
+        // a canceled order was refunded in full, one being canceled is being refunded right now
+        if order.status == "canceled" || order.status == "canceling" {
+            return Ok(Some(Err(CreditRefundError::NotRefundable { status: order.status })));
+        }
+
+        let refundable = (order.refundable() - db_get_refunds_in_flight(conn, order.id.clone())?).max(BigDecimal::from(0));
+        if amount > refundable {
+            return Ok(Some(Err(CreditRefundError::AmountTooHigh { amount, refundable })));
+        }
+
+        let entry = db_add_credit(conn, NewCreditEntry {
+            user_id: order.user_id,
+            amount: amount.clone(),
+            reason: "refund".to_string(),
+            order_id: Some(order.id.clone()),
+            note,
+            ..Default::default()
+        })?;
+
+        diesel::update(orders::table.find(order.id))
+            .set((
+                orders::refunded_amount.eq(orders::refunded_amount + amount),
+                orders::updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(Some(Ok(entry)))
+    })
+}
+
+// what was paid back on an order as store credit
+pub(crate) fn db_get_credit_refunded(
+    conn: &mut PgConnection,
+    order_id: String,
+) -> Result<BigDecimal, Error> {
+    let refunded = credit_ledger::table
+        .filter(credit_ledger::order_id.eq(order_id))
+        .filter(credit_ledger::reason.eq("refund"))
+        .select(diesel::dsl::sum(credit_ledger::amount))
+        .first::<Option<BigDecimal>>(conn)?;
+
+    Ok(refunded.unwrap_or_default())
+}
+
+pub(crate) fn db_get_credit_entries(
+    conn: &mut PgConnection,
+    user: String,
+) -> Result<Vec<CreditEntry>, Error> {
+    let entries = credit_ledger::table
+        .filter(credit_ledger::user_id.eq(user))
+        .order(credit_ledger::created_at.desc())
+        .load::<CreditEntry>(conn)?;
+
+    Ok(entries)
+}
+
+pub(crate) fn db_get_credit_balance(
+    conn: &mut PgConnection,
+    user: String,
+) -> Result<BigDecimal, Error> {
+    let balance = credit_ledger::table
+        .filter(credit_ledger::user_id.eq(user))
+        .select(diesel::dsl::sum(credit_ledger::amount))
+        .first::<Option<BigDecimal>>(conn)?;
+
+    Ok(balance.unwrap_or_default())
+}
+
+// take up to `amount` of the user's credit for a checkout, returns None when they have none
+// the user row is locked so two checkouts can not spend the same credit
+pub(crate) fn db_hold_credit(
+    conn: &mut PgConnection,
+    user: String,
+    amount: BigDecimal,
+) -> Result<Option<CreditEntry>, Error> {
+    conn.transaction(|conn| {
+        users::table
+            .find(user.clone())
+            .select(users::id)
+            .for_update()
+            .first::<String>(conn)?;
+
+        let held = db_get_credit_balance(conn, user.clone())?.min(amount);
+        if held <= BigDecimal::from(0) {
+            return Ok(None);
+        }
+
+        db_add_credit(conn, NewCreditEntry {
+            user_id: user,
+            amount: -held,
+            reason: "checkout".to_string(),
+            ..Default::default()
+        })
+        .map(Some)
+    })
+}
+
+pub(crate) fn db_attach_credit_to_session(
+    conn: &mut PgConnection,
+    entry_id: String,
+    session_id: String,
+) -> Result<CreditEntry, Error> {
+    let entry = diesel::update(credit_ledger::table.find(entry_id))
+        .set(credit_ledger::checkout_session_id.eq(session_id))
+        .get_result::<CreditEntry>(conn)?;
+
+    Ok(entry)
+}
+
+// a hold whose checkout session was never created never took effect, so it is simply removed
+pub(crate) fn db_cancel_credit_hold(
+    conn: &mut PgConnection,
+    entry_id: String,
+) -> Result<usize, Error> {
+    let deleted_entry = diesel::delete(credit_ledger::table.find(entry_id))
+        .execute(conn)?;
+
+    Ok(deleted_entry)
+}
+
+// give back the credit held by a checkout session that expired, safe to call more than once
+pub(crate) fn db_release_checkout_credit(
+    conn: &mut PgConnection,
+    session_id: String,
+) -> Result<Option<CreditEntry>, Error> {
+    conn.transaction(|conn| {
+        let entries = credit_ledger::table
+            .filter(credit_ledger::checkout_session_id.eq(session_id.clone()))
+            .for_update()
+            .load::<CreditEntry>(conn)?;
+
+        let held = entries.iter().map(|entry| entry.amount.clone()).sum::<BigDecimal>();
+        let user = match entries.first() {
+            Some(entry) if held < BigDecimal::from(0) => entry.user_id.clone(),
+            _ => return Ok(None),
+        };
+
+        db_add_credit(conn, NewCreditEntry {
+            user_id: user,
+            amount: -held,
+            reason: "checkout_released".to_string(),
+            checkout_session_id: Some(session_id),
+            ..Default::default()
+        })
+        .map(Some)
+    })
+}
+
+// link the credit spent in a completed checkout session to the order it paid for
+pub(crate) fn db_assign_checkout_credit(
+    conn: &mut PgConnection,
+    session_id: String,
+    order: String,
+) -> Result<usize, Error> {
+    let updated_entries = diesel::update(credit_ledger::table.filter(credit_ledger::checkout_session_id.eq(session_id)))
+        .set(credit_ledger::order_id.eq(order))
+        .execute(conn)?;
+
+    Ok(updated_entries)
+}
\ No newline at end of file
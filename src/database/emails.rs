@@ -0,0 +1,89 @@
+use diesel::dsl::{now, IntervalDsl};
+use diesel::{Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use diesel::result::Error;
+
+use crate::models::email::{NewOutboxEmail, OutboxEmail};
+use crate::schema::email_outbox::dsl::*;
+
+pub(crate) fn db_enqueue_email(
+    conn: &mut PgConnection,
+    new_email: NewOutboxEmail,
+) -> Result<OutboxEmail, Error> {
+    let email = diesel::insert_into(email_outbox)
+        .values(&new_email)
+        .get_result::<OutboxEmail>(conn)?;
+
+    Ok(email)
+}
+
+// lock the pending emails that are due and push their next attempt back by the lease
+// so another worker does not pick them up while they are being sent
+pub(crate) fn db_claim_due_emails(
+    conn: &mut PgConnection,
+    limit: i64,
+    lease_seconds: i32,
+) -> Result<Vec<OutboxEmail>, Error> {
+    conn.transaction(|conn| {
+        let due_emails = email_outbox
+            .filter(status.eq("pending"))
+            .filter(next_attempt_at.le(now))
+            .order(next_attempt_at.asc())
+            .limit(limit)
+            .for_update()
+            .skip_locked()
+            .load::<OutboxEmail>(conn)?;
+
+        let due_ids = due_emails.iter().map(|email| email.id.clone()).collect::<Vec<String>>();
+
+        diesel::update(email_outbox.filter(id.eq_any(due_ids)))
+            .set(next_attempt_at.eq(now + lease_seconds.seconds()))
+            .execute(conn)?;
+
+        Ok(due_emails)
+    })
+}
+
+pub(crate) fn db_mark_email_sent(
+    conn: &mut PgConnection,
+    email_id: String,
+) -> Result<OutboxEmail, Error> {
+    let email = diesel::update(email_outbox.find(email_id))
+        .set((
+            status.eq("sent"),
+            attempts.eq(attempts + 1),
+            last_error.eq(None::<String>),
+            sent_at.eq(now),
+        ))
+        .get_result::<OutboxEmail>(conn)?;
+
+    Ok(email)
+}
+
+// record a failed attempt, retrying after `retry_in_seconds` or giving up when it is None
+pub(crate) fn db_mark_email_failed(
+    conn: &mut PgConnection,
+    email_id: String,
+    error: String,
+    retry_in_seconds: Option<i32>,
+) -> Result<OutboxEmail, Error> {
+    let target = email_outbox.find(email_id);
+
+    let email = match retry_in_seconds {
+        Some(retry_in_seconds) => diesel::update(target)
+            .set((
+                attempts.eq(attempts + 1),
+                last_error.eq(error),
+                next_attempt_at.eq(now + retry_in_seconds.seconds()),
+            ))
+            .get_result::<OutboxEmail>(conn)?,
+        None => diesel::update(target)
+            .set((
+                status.eq("failed"),
+                attempts.eq(attempts + 1),
+                last_error.eq(error),
+            ))
+            .get_result::<OutboxEmail>(conn)?,
+    };
+
+    Ok(email)
+}
@@ -0,0 +1,106 @@
+use diesel::result::Error;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::cart::CartItem;
+use crate::models::guest_cart::{GuestCart, GuestCartItem};
+use crate::schema::{guest_cart_items, guest_carts};
+
+use super::carts::db_merge_into_cart;
+
+pub(crate) fn db_create_guest_cart (
+    conn: &mut PgConnection,
+    expires_at: chrono::NaiveDateTime,
+) -> Result<GuestCart, Error> {
+    let guest_cart = diesel::insert_into(guest_carts::table)
+        .values(guest_carts::expires_at.eq(expires_at))
+        .get_result::<GuestCart>(conn)?;
+
+    Ok(guest_cart)
+}
+
+// only returns carts that have not expired yet
+pub(crate) fn db_get_guest_cart (
+    conn: &mut PgConnection,
+    guest_id: String,
+) -> Result<Option<GuestCart>, Error> {
+    let guest_cart = guest_carts::table
+        .find(guest_id)
+        .filter(guest_carts::expires_at.gt(chrono::Utc::now().naive_utc()))
+        .first::<GuestCart>(conn)
+        .optional()?;
+
+    Ok(guest_cart)
+}
+
+pub(crate) fn db_get_guest_cart_items (
+    conn: &mut PgConnection,
+    guest_id: String,
+) -> Result<Option<Vec<GuestCartItem>>, Error> {
+    let cart_items = guest_cart_items::table
+        .filter(guest_cart_items::guest_cart_id.eq(guest_id))
+        .load::<GuestCartItem>(conn)?;
+
+    Ok(Some(cart_items))
+}
+
+// add a line or overwrite the quantity of the line already in the cart
+pub(crate) fn db_upsert_guest_cart_item (
+    conn: &mut PgConnection,
+    new_cart_item: GuestCartItem,
+) -> Result<GuestCartItem, Error> {
+    let cart_item = diesel::insert_into(guest_cart_items::table)
+        .values(&new_cart_item)
+        .on_conflict((guest_cart_items::guest_cart_id, guest_cart_items::product_id))
+        .do_update()
+        .set(guest_cart_items::quantity.eq(new_cart_item.quantity))
+        .get_result::<GuestCartItem>(conn)?;
+
+    Ok(cart_item)
+}
+
+pub(crate) fn db_replace_guest_cart_items (
+    conn: &mut PgConnection,
+    guest_id: String,
+    new_cart_items: Vec<GuestCartItem>,
+) -> Result<Option<Vec<GuestCartItem>>, Error> {
+    conn.transaction(|conn| {
+        diesel::delete(guest_cart_items::table.filter(guest_cart_items::guest_cart_id.eq(guest_id.clone())))
+            .execute(conn)?;
+
+        diesel::insert_into(guest_cart_items::table)
+            .values(&new_cart_items)
+            .execute(conn)?;
+
+        db_get_guest_cart_items(conn, guest_id)
+    })
+}
+
+pub(crate) fn db_delete_expired_guest_carts (
+    conn: &mut PgConnection,
+) -> Result<usize, Error> {
+    let deleted_guest_carts = diesel::delete(guest_carts::table.filter(guest_carts::expires_at.le(chrono::Utc::now().naive_utc())))
+        .execute(conn)?;
+
+    Ok(deleted_guest_carts)
+}
+
+// move a guest cart into a user's cart and delete the guest cart
+pub(crate) fn db_merge_guest_cart (
+    conn: &mut PgConnection,
+    guest_id: String,
+    user: String,
+) -> Result<Option<Vec<CartItem>>, Error> {
+    conn.transaction(|conn| {
+        let guest_items = db_get_guest_cart_items(conn, guest_id.clone())?.unwrap_or_default();
+
+        let cart_items = db_merge_into_cart(
+            conn,
+            user,
+            guest_items.into_iter().map(|item| (item.product_id, item.quantity)).collect(),
+        )?;
+
+        diesel::delete(guest_carts::table.find(guest_id)).execute(conn)?;
+
+        Ok(cart_items)
+    })
+}
@@ -3,4 +3,14 @@ pub mod products;
 pub mod tests;
 pub mod users;
 pub mod carts;
-pub mod orders;
\ No newline at end of file
+pub mod orders;
+pub mod emails;
+pub mod guest_carts;
+pub mod abandoned_carts;
+pub mod promotions;
+pub mod credit;
+pub mod shipping;
+pub mod addresses;
+pub mod shipments;
+pub mod returns;
+pub mod api_keys;
\ No newline at end of file
@@ -1,10 +1,18 @@
-use diesel::{RunQueryDsl, QueryDsl, ExpressionMethods};
+use bigdecimal::BigDecimal;
+use diesel::{Connection, RunQueryDsl, QueryDsl, ExpressionMethods, OptionalExtension};
 use diesel::{result::Error, PgConnection};
 
-use crate::models::order::{Order, NewOrder, ExpandedOrder, OrderProduct};
+use crate::models::address::ShippingAddress;
+use crate::models::credit::NewCreditEntry;
+use crate::models::order::{CancelError, Order, NewOrder, ExpandedOrder, OrderProduct};
+use crate::models::pagination::{Page, PageQuery};
+use crate::models::shipment::quantities;
+use crate::schema::email_outbox;
 use crate::schema::orders::dsl::*;
 
-use super::products::db_expand_products;
+use super::credit::{db_add_credit, db_get_credit_refunded};
+use super::products::{db_expand_products, db_restock_products};
+use super::shipments::db_get_shipments_by_order_id;
 
 pub(crate) fn db_get_all_orders(
     conn: &mut PgConnection,
@@ -41,9 +49,10 @@ pub(crate) fn db_get_expanded_orders(
                 })
                 .collect::<Vec<OrderProduct>>();
     
-            ExpandedOrder::new(order.clone(), expanded_products)
+            let shipments = db_get_shipments_by_order_id(conn, order.id.clone())?;
+
+            Ok(ExpandedOrder::new(order.clone(), expanded_products, shipments))
         })
-        .map(Result::Ok)
         .collect::<Vec<Result<ExpandedOrder, Error>>>();
     
     let expanded_orders = expanded_orders.into_iter()
@@ -71,6 +80,53 @@ pub(crate) fn db_get_expanded_order_by_id(
         .find(order_id)
         .first::<Order>(conn)?;
 
+    expand_order(conn, order)
+}
+
+// the orders of a user, newest first, a page at a time
+pub(crate) fn db_get_expanded_orders_page_by_user_id(
+    conn: &mut PgConnection,
+    user: String,
+    query: &PageQuery,
+) -> Result<Page<ExpandedOrder>, Error> {
+    let total = orders
+        .filter(user_id.eq(user.clone()))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    let orders_by_user_id = orders
+        .filter(user_id.eq(user))
+        .order(created_at.desc())
+        .limit(query.per_page())
+        .offset(query.offset())
+        .load::<Order>(conn)?;
+
+    let expanded_orders = orders_by_user_id.into_iter()
+        .map(|order| expand_order(conn, order))
+        .collect::<Result<Vec<ExpandedOrder>, Error>>()?;
+
+    Ok(Page::new(expanded_orders, query, total))
+}
+
+// an order only when it belongs to the user
+pub(crate) fn db_get_expanded_order_by_user_id(
+    conn: &mut PgConnection,
+    user: String,
+    order_id: String,
+) -> Result<Option<ExpandedOrder>, Error> {
+    let order = orders
+        .find(order_id)
+        .filter(user_id.eq(user))
+        .first::<Order>(conn)
+        .optional()?;
+
+    order.map(|order| expand_order(conn, order)).transpose()
+}
+
+fn expand_order(
+    conn: &mut PgConnection,
+    order: Order,
+) -> Result<ExpandedOrder, Error> {
     let product_ids = order.products.as_object().unwrap();
     let expanded_products = product_ids.iter()
         .map(|(product_id, quantity)| {
@@ -82,9 +138,9 @@ pub(crate) fn db_get_expanded_order_by_id(
         })
         .collect::<Vec<OrderProduct>>();
 
-    let expanded_order = ExpandedOrder::new(order, expanded_products);
+    let shipments = db_get_shipments_by_order_id(conn, order.id.clone())?;
 
-    Ok(expanded_order)
+    Ok(ExpandedOrder::new(order, expanded_products, shipments))
 }
 
 pub(crate) fn db_get_orders_by_user_id(
@@ -124,9 +180,10 @@ pub(crate) fn db_get_expanded_orders_by_user_id(
                 })
                 .collect::<Vec<OrderProduct>>();
     
-            ExpandedOrder::new(order.clone(), expanded_products)
+            let shipments = db_get_shipments_by_order_id(conn, order.id.clone())?;
+
+            Ok(ExpandedOrder::new(order.clone(), expanded_products, shipments))
         })
-        .map(Result::Ok)
         .collect::<Vec<Result<ExpandedOrder, Error>>>();
     
     let expanded_orders = expanded_orders.into_iter()
@@ -159,13 +216,150 @@ pub(crate) fn db_update_order(
     Ok(order)
 }
 
+// every address column is written, so parts left out of the correction are cleared
+pub(crate) fn db_update_order_address(
+    conn: &mut PgConnection,
+    order_id: String,
+    address: ShippingAddress,
+) -> Result<Order, Error> {
+    let order = diesel::update(orders.find(order_id))
+        .set((
+            name.eq(address.name),
+            phone.eq(address.phone),
+            address_line1.eq(address.line1),
+            address_line2.eq(address.line2),
+            address_city.eq(address.city),
+            address_state.eq(address.state),
+            address_postal_code.eq(address.postal_code),
+            address_country.eq(address.country),
+            updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .get_result::<Order>(conn)?;
+
+    Ok(order)
+}
+
+// the order being canceled and what is paid back to the card for it
+type CancelClaim = (Order, BigDecimal);
+
+// the owner calls off an order that has not gone out. it is held as canceling while its payment is refunded, an order
+// left canceling by a failed request can be canceled again. the card gets back what was paid with it and not refunded
+// to it yet, but no more than earlier refunds to store credit leave to refund
+pub(crate) fn db_start_cancel_order(
+    conn: &mut PgConnection,
+    user: String,
+    order_id: String,
+) -> Result<Option<Result<CancelClaim, CancelError>>, Error> {
+    conn.transaction(|conn| {
+        let order = orders
+            .find(order_id.clone())
+            .filter(user_id.eq(user.clone()))
+            .for_update()
+            .first::<Order>(conn)
+            .optional()?;
+
+        let order = match order {
+            Some(order) => order,
+            None => return Ok(None),
+        };
+
+        if order.status != "processing" && order.status != "canceling" {
+            return Ok(Some(Err(CancelError::NotCancelable { status: order.status })));
+        }
+
+        if order.payment_intent_id.is_none() {
+            return Ok(Some(Err(CancelError::NoPayment)));
+        }
+
+        let refunded_to_card = order.refunded_amount.clone() - db_get_credit_refunded(conn, order_id.clone())?;
+        let card_refund = (order.amount_paid.clone() - refunded_to_card)
+            .min(order.refundable())
+            .max(BigDecimal::from(0));
+
+        let order = diesel::update(orders.find(order_id))
+            .set((status.eq("canceling"), updated_at.eq(chrono::Local::now().naive_local())))
+            .get_result::<Order>(conn)?;
+
+        Ok(Some(Ok((order, card_refund))))
+    })
+}
+
+// the refund failed, the order goes back to being fulfilled
+pub(crate) fn db_abort_cancel_order(
+    conn: &mut PgConnection,
+    order_id: String,
+) -> Result<(), Error> {
+    diesel::update(orders.find(order_id).filter(status.eq("canceling")))
+        .set(status.eq("processing"))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// the payment was refunded, the stock goes back and the store credit spent on the order is returned as far as
+// the card refund and earlier refunds leave anything to refund
+pub(crate) fn db_cancel_order(
+    conn: &mut PgConnection,
+    user: String,
+    order_id: String,
+    refunded: BigDecimal,
+) -> Result<Order, Error> {
+    conn.transaction(|conn| {
+        let order = orders
+            .find(order_id.clone())
+            .for_update()
+            .first::<Order>(conn)?;
+
+        // a retry whose refund stripe answered with the one it already made, a concurrent retry that failed may have put
+        // the order back to processing but the refund went through
+        if order.status != "canceling" && order.status != "processing" {
+            return Ok(order);
+        }
+
+        db_restock_products(conn, quantities(&order.products).into_iter().collect())?;
+
+        let credit_back = order.store_credit.clone().unwrap_or_default().min(order.refundable() - refunded.clone());
+        let mut refunded = refunded;
+        if credit_back > BigDecimal::from(0) {
+            db_add_credit(conn, NewCreditEntry {
+                user_id: order.user_id.clone(),
+                amount: credit_back.clone(),
+                reason: "refund".to_string(),
+                order_id: Some(order.id.clone()),
+                note: Some("order canceled".to_string()),
+                ..Default::default()
+            })?;
+            refunded += credit_back;
+        }
+
+        let current_time = chrono::Local::now().naive_local();
+        let order = diesel::update(orders.find(order_id))
+            .set((
+                status.eq("canceled"),
+                refunded_amount.eq(refunded_amount + refunded),
+                canceled_by.eq(user),
+                canceled_at.eq(current_time),
+                updated_at.eq(current_time),
+            ))
+            .get_result::<Order>(conn)?;
+
+        Ok(order)
+    })
+}
+
+// the emails queued for the order go with it, the outbox doesn't cascade
 pub(crate) fn db_delete_order(
     conn: &mut PgConnection,
     order_id: String,
 ) -> Result<usize, Error> {
-    let deleted_order = diesel::delete(orders.find(order_id))
-        .execute(conn)?;
+    conn.transaction(|conn| {
+        diesel::delete(email_outbox::table.filter(email_outbox::order_id.eq(order_id.clone())))
+            .execute(conn)?;
+
+        let deleted_order = diesel::delete(orders.find(order_id))
+            .execute(conn)?;
 
-    Ok(deleted_order)
+        Ok(deleted_order)
+    })
 }
 
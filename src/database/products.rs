@@ -1,5 +1,5 @@
 use diesel::result::Error;
-use diesel::{ExpressionMethods, PgConnection, PgTextExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::{Connection, ExpressionMethods, PgConnection, PgTextExpressionMethods, QueryDsl, RunQueryDsl};
 
 use crate::models::product::{NewProduct, Product};
 use crate::schema::products::dsl::*;
@@ -132,6 +132,50 @@ pub(crate) fn db_update_product(
     Ok(product)
 }
 
+// put quantities back on the shelf, used when a checkout expires and when goods come back
+pub(crate) fn db_restock_products(
+    conn: &mut PgConnection,
+    lines: Vec<(String, i32)>,
+) -> Result<(), Error> {
+    for (product_id, quantity) in lines {
+        let product = db_get_product_by_id(conn, product_id)?;
+        db_update_product(conn, NewProduct{
+            id: Some(product.id.clone()),
+            inventory: Some(quantity + product.inventory.unwrap_or(0)),
+            ..Default::default()
+        })?;
+    }
+
+    Ok(())
+}
+
+// take quantities off the shelf for a checkout, all of them or none when one product is short
+pub(crate) fn db_take_stock(
+    conn: &mut PgConnection,
+    lines: Vec<(String, i32)>,
+) -> Result<bool, Error> {
+    let taken = conn.transaction(|conn| {
+        let current_time = chrono::Local::now().naive_local();
+        for (product_id, quantity) in lines {
+            let updated = diesel::update(products.find(product_id).filter(inventory.ge(quantity)))
+                .set((inventory.eq(inventory - quantity), last_updated.eq(current_time)))
+                .execute(conn)?;
+
+            if updated == 0 {
+                return Err(Error::RollbackTransaction);
+            }
+        }
+
+        Ok(())
+    });
+
+    match taken {
+        Ok(()) => Ok(true),
+        Err(Error::RollbackTransaction) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
 pub(crate) fn db_delete_product(
     conn: &mut PgConnection,
     product_id: String,
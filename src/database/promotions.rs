@@ -0,0 +1,90 @@
+use diesel::result::Error;
+use diesel::{BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::promotion::{NewPromotion, Promotion};
+use crate::schema::promotions::dsl::*;
+
+pub(crate) fn db_get_all_promotions(
+    conn: &mut PgConnection,
+) -> Result<Option<Vec<Promotion>>, Error> {
+    let all_promotions = promotions
+        .order(created_at.desc())
+        .load::<Promotion>(conn)?;
+
+    Ok(Some(all_promotions))
+}
+
+// codes are stored upper case so they can be typed in any case
+pub(crate) fn db_get_promotion_by_code(
+    conn: &mut PgConnection,
+    promotion_code: String,
+) -> Result<Option<Promotion>, Error> {
+    let promotion = promotions
+        .filter(code.eq(promotion_code.trim().to_uppercase()))
+        .first::<Promotion>(conn)
+        .optional()?;
+
+    Ok(promotion)
+}
+
+pub(crate) fn db_create_promotion(
+    conn: &mut PgConnection,
+    new_promotion: NewPromotion,
+) -> Result<Promotion, Error> {
+    let promotion = diesel::insert_into(promotions)
+        .values(&new_promotion)
+        .get_result::<Promotion>(conn)?;
+
+    Ok(promotion)
+}
+
+pub(crate) fn db_update_promotion(
+    conn: &mut PgConnection,
+    promotion_id: String,
+    new_promotion: NewPromotion,
+) -> Result<Promotion, Error> {
+    let promotion = diesel::update(promotions.find(promotion_id))
+        .set(&new_promotion)
+        .get_result::<Promotion>(conn)?;
+
+    Ok(promotion)
+}
+
+pub(crate) fn db_delete_promotion(
+    conn: &mut PgConnection,
+    promotion_id: String,
+) -> Result<usize, Error> {
+    let deleted_promotion = diesel::delete(promotions.find(promotion_id))
+        .execute(conn)?;
+
+    Ok(deleted_promotion)
+}
+
+// count a use of the promotion when a checkout session is created with it, returns 0 when the usage limit was
+// already reached
+pub(crate) fn db_redeem_promotion(
+    conn: &mut PgConnection,
+    promotion_id: String,
+) -> Result<usize, Error> {
+    let redeemed = diesel::update(
+        promotions
+            .find(promotion_id)
+            .filter(usage_limit.is_null().or(times_redeemed.lt(usage_limit.assume_not_null()))),
+    )
+    .set(times_redeemed.eq(times_redeemed + 1))
+    .execute(conn)?;
+
+    Ok(redeemed)
+}
+
+// give back the use a checkout session reserved when it expires without being paid
+pub(crate) fn db_release_promotion(
+    conn: &mut PgConnection,
+    promotion_id: String,
+) -> Result<usize, Error> {
+    let released = diesel::update(promotions.find(promotion_id).filter(times_redeemed.gt(0)))
+        .set(times_redeemed.eq(times_redeemed - 1))
+        .execute(conn)?;
+
+    Ok(released)
+}
\ No newline at end of file
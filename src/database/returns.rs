@@ -0,0 +1,388 @@
+use bigdecimal::BigDecimal;
+use diesel::dsl::now;
+use diesel::result::Error;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::order::Order;
+use crate::models::order_return::{NewOrderReturn, OrderReturn, ReturnError, ReturnRequest};
+use crate::models::shipment::quantities;
+use crate::schema::{orders, returns};
+
+use super::credit::db_get_credit_refunded;
+use super::products::db_restock_products;
+use super::shipments::db_get_shipments_by_order_id;
+
+pub(crate) fn db_get_returns(
+    conn: &mut PgConnection,
+    status: Option<String>,
+) -> Result<Vec<OrderReturn>, Error> {
+    let mut query = returns::table
+        .order(returns::created_at.desc())
+        .into_boxed();
+
+    if let Some(status) = status {
+        query = query.filter(returns::status.eq(status));
+    }
+
+    let all_returns = query.load::<OrderReturn>(conn)?;
+
+    Ok(all_returns)
+}
+
+pub(crate) fn db_get_returns_by_user_id(
+    conn: &mut PgConnection,
+    user: String,
+) -> Result<Vec<OrderReturn>, Error> {
+    let user_returns = returns::table
+        .filter(returns::user_id.eq(user))
+        .order(returns::created_at.desc())
+        .load::<OrderReturn>(conn)?;
+
+    Ok(user_returns)
+}
+
+pub(crate) fn db_get_return(
+    conn: &mut PgConnection,
+    return_id: String,
+) -> Result<Option<OrderReturn>, Error> {
+    let order_return = returns::table
+        .find(return_id)
+        .first::<OrderReturn>(conn)
+        .optional()?;
+
+    Ok(order_return)
+}
+
+// a customer asks to send back lines of one of their orders that went out
+pub(crate) fn db_create_return(
+    conn: &mut PgConnection,
+    user: String,
+    order_id: String,
+    request: ReturnRequest,
+) -> Result<Option<Result<OrderReturn, ReturnError>>, Error> {
+    conn.transaction(|conn| {
+        let order = orders::table
+            .find(order_id.clone())
+            .filter(orders::user_id.eq(user.clone()))
+            .for_update()
+            .first::<Order>(conn)
+            .optional()?;
+
+        let order = match order {
+            Some(order) => order,
+            None => return Ok(None),
+        };
+
+        if !["partially_shipped", "shipped", "delievered"].contains(&order.status.as_str()) {
+            return Ok(Some(Err(ReturnError::NotShipped { status: order.status })));
+        }
+
+        if request.products.is_empty() {
+            return Ok(Some(Err(ReturnError::NothingToReturn)));
+        }
+
+        // only what went out in a shipment can come back. an order marked shipped without shipments on record sent
+        // everything
+        let shipments = db_get_shipments_by_order_id(conn, order_id.clone())?;
+        let mut returnable = quantities(&order.products);
+        if !shipments.is_empty() || order.status == "partially_shipped" {
+            returnable.values_mut().for_each(|quantity| *quantity = 0);
+            for shipment in shipments {
+                for (product_id, quantity) in shipment.lines() {
+                    if let Some(shipped) = returnable.get_mut(&product_id) {
+                        *shipped += quantity;
+                    }
+                }
+            }
+        }
+
+        // lines already on a return that was not rejected cannot be returned twice
+        let order_returns = returns::table
+            .filter(returns::order_id.eq(order_id.clone()))
+            .filter(returns::status.ne("rejected"))
+            .load::<OrderReturn>(conn)?;
+        for order_return in order_returns {
+            for (product_id, quantity) in order_return.lines() {
+                if let Some(left) = returnable.get_mut(&product_id) {
+                    *left -= quantity;
+                }
+            }
+        }
+
+        for (product_id, quantity) in request.products.iter() {
+            let left = match returnable.get(product_id) {
+                Some(left) => *left,
+                None => return Ok(Some(Err(ReturnError::NotInOrder { product_id: product_id.clone() }))),
+            };
+
+            if *quantity <= 0 {
+                return Ok(Some(Err(ReturnError::InvalidQuantity { product_id: product_id.clone(), quantity: *quantity })));
+            }
+            if *quantity > left {
+                return Ok(Some(Err(ReturnError::TooMany { product_id: product_id.clone(), returnable: left.max(0) })));
+            }
+        }
+
+        let products = request.products.into_iter()
+            .map(|(product_id, quantity)| (product_id, serde_json::Value::Number(serde_json::Number::from(quantity))))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+
+        let order_return = diesel::insert_into(returns::table)
+            .values(&NewOrderReturn {
+                order_id,
+                user_id: user,
+                products: serde_json::Value::Object(products),
+                reason: request.reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty()),
+            })
+            .get_result::<OrderReturn>(conn)?;
+
+        Ok(Some(Ok(order_return)))
+    })
+}
+
+// approve or reject a return that is waiting on a decision
+pub(crate) fn db_decide_return(
+    conn: &mut PgConnection,
+    return_id: String,
+    approved: bool,
+    note: Option<String>,
+) -> Result<Option<Result<OrderReturn, ReturnError>>, Error> {
+    conn.transaction(|conn| {
+        let order_return = match lock_return(conn, return_id.clone())? {
+            Some(order_return) => order_return,
+            None => return Ok(None),
+        };
+
+        if order_return.status != "requested" {
+            return Ok(Some(Err(ReturnError::WrongStatus { status: order_return.status, expected: "requested" })));
+        }
+
+        let order_return = match approved {
+            true => diesel::update(returns::table.find(return_id))
+                .set((returns::status.eq("approved"), returns::note.eq(note), returns::approved_at.eq(now)))
+                .get_result::<OrderReturn>(conn)?,
+            false => diesel::update(returns::table.find(return_id))
+                .set((returns::status.eq("rejected"), returns::note.eq(note)))
+                .get_result::<OrderReturn>(conn)?,
+        };
+
+        Ok(Some(Ok(order_return)))
+    })
+}
+
+// the goods arrived back, they go back into the inventory
+pub(crate) fn db_receive_return(
+    conn: &mut PgConnection,
+    return_id: String,
+) -> Result<Option<Result<OrderReturn, ReturnError>>, Error> {
+    conn.transaction(|conn| {
+        let order_return = match lock_return(conn, return_id.clone())? {
+            Some(order_return) => order_return,
+            None => return Ok(None),
+        };
+
+        if order_return.status != "approved" {
+            return Ok(Some(Err(ReturnError::WrongStatus { status: order_return.status, expected: "approved" })));
+        }
+
+        db_restock_products(conn, order_return.lines().into_iter().collect())?;
+
+        let order_return = diesel::update(returns::table.find(return_id))
+            .set((returns::status.eq("received"), returns::received_at.eq(now)))
+            .get_result::<OrderReturn>(conn)?;
+
+        Ok(Some(Ok(order_return)))
+    })
+}
+
+// the claimed return and the payment intent it is refunded from
+type RefundClaim = (OrderReturn, String);
+
+// claim a received return for refunding before stripe is asked, a second refund of it is turned away while
+// this one is in flight. a return left refunding by a failed request is retried for the amount it was claimed for
+pub(crate) fn db_start_return_refund(
+    conn: &mut PgConnection,
+    return_id: String,
+    amount: Option<BigDecimal>,
+) -> Result<Option<Result<RefundClaim, ReturnError>>, Error> {
+    conn.transaction(|conn| {
+        let order_return = match lock_return(conn, return_id.clone())? {
+            Some(order_return) => order_return,
+            None => return Ok(None),
+        };
+
+        if !["received", "refunding"].contains(&order_return.status.as_str()) {
+            return Ok(Some(Err(ReturnError::WrongStatus { status: order_return.status, expected: "received" })));
+        }
+
+        let order = orders::table
+            .find(order_return.order_id.clone())
+            .for_update()
+            .first::<Order>(conn)?;
+
+        let payment_intent_id = match order.payment_intent_id.clone() {
+            Some(payment_intent_id) => payment_intent_id,
+            None => return Ok(Some(Err(ReturnError::NoPayment))),
+        };
+
+        if order_return.status == "refunding" {
+            return Ok(Some(Ok((order_return, payment_intent_id))));
+        }
+
+        let in_flight = db_get_refunds_in_flight(conn, order.id.clone())?;
+        let refundable = order.paid_for(&order_return.lines())
+            .min((order.refundable() - in_flight).max(BigDecimal::from(0)));
+        let amount = amount.unwrap_or(refundable.clone()).with_scale(2);
+
+        if amount <= BigDecimal::from(0) {
+            return Ok(Some(Err(ReturnError::InvalidAmount { amount })));
+        }
+        if amount > refundable {
+            return Ok(Some(Err(ReturnError::AmountTooHigh { amount, refundable })));
+        }
+
+        let order_return = diesel::update(returns::table.find(return_id))
+            .set((returns::status.eq("refunding"), returns::refund_amount.eq(amount)))
+            .get_result::<OrderReturn>(conn)?;
+
+        Ok(Some(Ok((order_return, payment_intent_id))))
+    })
+}
+
+// stripe turned the refund down, the return can be refunded again
+pub(crate) fn db_release_return_refund(
+    conn: &mut PgConnection,
+    return_id: String,
+) -> Result<(), Error> {
+    diesel::update(returns::table.find(return_id).filter(returns::status.eq("refunding")))
+        .set((returns::status.eq("received"), returns::refund_amount.eq(None::<BigDecimal>)))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// returns of the order being refunded right now, they are not in its refunded amount yet
+pub(crate) fn db_get_refunds_in_flight(
+    conn: &mut PgConnection,
+    order_id: String,
+) -> Result<BigDecimal, Error> {
+    let in_flight = returns::table
+        .filter(returns::order_id.eq(order_id))
+        .filter(returns::status.eq("refunding"))
+        .select(diesel::dsl::sum(returns::refund_amount))
+        .first::<Option<BigDecimal>>(conn)?;
+
+    Ok(in_flight.unwrap_or_default())
+}
+
+// the stripe refund went through, the order is marked returned once every line came back.
+// a return the charge.refunded webhook already closed is left as it is
+pub(crate) fn db_record_return_refund(
+    conn: &mut PgConnection,
+    return_id: String,
+    amount: BigDecimal,
+    refund_id: String,
+) -> Result<OrderReturn, Error> {
+    conn.transaction(|conn| {
+        let order_return = diesel::update(returns::table
+            .find(return_id.clone())
+            .filter(returns::status.eq_any(["received", "refunding"])))
+            .set((
+                returns::status.eq("refunded"),
+                returns::refund_amount.eq(amount.clone()),
+                returns::stripe_refund_id.eq(refund_id),
+                returns::refunded_at.eq(now),
+            ))
+            .get_result::<OrderReturn>(conn)
+            .optional()?;
+
+        let order_return = match order_return {
+            Some(order_return) => order_return,
+            None => return returns::table.find(return_id).first::<OrderReturn>(conn),
+        };
+
+        let order = diesel::update(orders::table.find(order_return.order_id.clone()))
+            .set((
+                orders::refunded_amount.eq(orders::refunded_amount + amount),
+                orders::updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .get_result::<Order>(conn)?;
+
+        let mut remaining = quantities(&order.products);
+        let refunded_returns = returns::table
+            .filter(returns::order_id.eq(order.id.clone()))
+            .filter(returns::status.eq("refunded"))
+            .load::<OrderReturn>(conn)?;
+        for refunded_return in refunded_returns {
+            for (product_id, quantity) in refunded_return.lines() {
+                if let Some(left) = remaining.get_mut(&product_id) {
+                    *left -= quantity;
+                }
+            }
+        }
+
+        if remaining.values().all(|left| *left <= 0) {
+            diesel::update(orders::table.find(order.id))
+                .set(orders::status.eq("returned"))
+                .execute(conn)?;
+        }
+
+        Ok(order_return)
+    })
+}
+
+// bring an order in line with what stripe says was refunded on its payment, the refunds carry the return they were issued for.
+// refunds given as store credit are added on top
+pub(crate) fn db_reconcile_refunds(
+    conn: &mut PgConnection,
+    payment_intent: String,
+    amount_refunded: BigDecimal,
+    refunds: Vec<(String, String, BigDecimal)>,
+) -> Result<Option<Order>, Error> {
+    conn.transaction(|conn| {
+        let order_id = orders::table
+            .filter(orders::payment_intent_id.eq(payment_intent))
+            .select(orders::id)
+            .for_update()
+            .first::<String>(conn)
+            .optional()?;
+
+        let order_id = match order_id {
+            Some(order_id) => order_id,
+            None => return Ok(None),
+        };
+
+        let credit_refunded = db_get_credit_refunded(conn, order_id.clone())?;
+        let order = diesel::update(orders::table.find(order_id))
+            .set(orders::refunded_amount.eq(amount_refunded + credit_refunded))
+            .get_result::<Order>(conn)?;
+
+        // a refund whose result never made it into the database still closes its return
+        for (return_id, refund_id, amount) in refunds {
+            diesel::update(returns::table
+                .find(return_id)
+                .filter(returns::order_id.eq(order.id.clone()))
+                .filter(returns::status.eq_any(["received", "refunding"])))
+                .set((
+                    returns::status.eq("refunded"),
+                    returns::refund_amount.eq(amount),
+                    returns::stripe_refund_id.eq(refund_id),
+                    returns::refunded_at.eq(now),
+                ))
+                .execute(conn)?;
+        }
+
+        Ok(Some(order))
+    })
+}
+
+fn lock_return(
+    conn: &mut PgConnection,
+    return_id: String,
+) -> Result<Option<OrderReturn>, Error> {
+    returns::table
+        .find(return_id)
+        .for_update()
+        .first::<OrderReturn>(conn)
+        .optional()
+}
\ No newline at end of file
@@ -0,0 +1,103 @@
+use diesel::result::Error;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::order::Order;
+use crate::models::shipment::{unshipped, NewShipment, Shipment, ShipmentError, ShipmentPayload};
+use crate::schema::{orders, shipments};
+
+pub(crate) fn db_get_shipments_by_order_id(
+    conn: &mut PgConnection,
+    order: String,
+) -> Result<Vec<Shipment>, Error> {
+    let order_shipments = shipments::table
+        .filter(shipments::order_id.eq(order))
+        .order(shipments::shipped_at.asc())
+        .load::<Shipment>(conn)?;
+
+    Ok(order_shipments)
+}
+
+// record a package going out and move the order to shipped once nothing is left to send
+pub(crate) fn db_create_shipment(
+    conn: &mut PgConnection,
+    order_id: String,
+    payload: ShipmentPayload,
+    created_by: String,
+) -> Result<Option<Result<Shipment, ShipmentError>>, Error> {
+    conn.transaction(|conn| {
+        let order = orders::table
+            .find(order_id.clone())
+            .for_update()
+            .first::<Order>(conn)
+            .optional()?;
+
+        let order = match order {
+            Some(order) => order,
+            None => return Ok(None),
+        };
+
+        if order.status != "processing" && order.status != "partially_shipped" {
+            return Ok(Some(Err(ShipmentError::OrderClosed { status: order.status })));
+        }
+
+        let carrier = payload.carrier.trim().to_string();
+        if carrier.is_empty() {
+            return Ok(Some(Err(ShipmentError::MissingCarrier)));
+        }
+
+        let mut remaining = unshipped(&order.products, &db_get_shipments_by_order_id(conn, order_id.clone())?);
+        if remaining.is_empty() {
+            return Ok(Some(Err(ShipmentError::NothingToShip)));
+        }
+
+        let lines = payload.products.clone().unwrap_or(remaining.clone());
+        for (product_id, quantity) in lines.iter() {
+            let left = match remaining.get_mut(product_id) {
+                Some(left) => left,
+                None if order.products.get(product_id).is_some() => return Ok(Some(Err(ShipmentError::TooMany { product_id: product_id.clone(), remaining: 0 }))),
+                None => return Ok(Some(Err(ShipmentError::NotInOrder { product_id: product_id.clone() }))),
+            };
+
+            if *quantity <= 0 {
+                return Ok(Some(Err(ShipmentError::InvalidQuantity { product_id: product_id.clone(), quantity: *quantity })));
+            }
+            if *quantity > *left {
+                return Ok(Some(Err(ShipmentError::TooMany { product_id: product_id.clone(), remaining: *left })));
+            }
+            *left -= quantity;
+        }
+        if lines.is_empty() {
+            return Ok(Some(Err(ShipmentError::NothingToShip)));
+        }
+
+        let products = lines.into_iter()
+            .map(|(product_id, quantity)| (product_id, serde_json::Value::Number(serde_json::Number::from(quantity))))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+
+        let shipment = diesel::insert_into(shipments::table)
+            .values(&NewShipment {
+                order_id: order_id.clone(),
+                products: serde_json::Value::Object(products),
+                carrier,
+                tracking_number: payload.tracking_number.clone().map(|number| number.trim().to_string()).filter(|number| !number.is_empty()),
+                tracking_url: payload.tracking_url(),
+                shipped_at: payload.shipped_at,
+                created_by,
+            })
+            .get_result::<Shipment>(conn)?;
+
+        let status = match remaining.values().all(|left| *left == 0) {
+            true => "shipped",
+            false => "partially_shipped",
+        };
+
+        diesel::update(orders::table.find(order_id))
+            .set((
+                orders::status.eq(status),
+                orders::updated_at.eq(chrono::Local::now().naive_local()),
+            ))
+            .execute(conn)?;
+
+        Ok(Some(Ok(shipment)))
+    })
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+use diesel::result::Error;
+use diesel::{Connection, ExpressionMethods, OptionalExtension, PgArrayExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::models::shipping::{NewShippingMethod, NewShippingZone, ShippingError, ShippingMethod, ShippingZone, ShippingZoneDetails, MAX_ACTIVE_METHODS};
+use crate::schema::{shipping_methods, shipping_zones};
+
+pub(crate) fn db_get_shipping_zones(
+    conn: &mut PgConnection,
+) -> Result<Vec<ShippingZoneDetails>, Error> {
+    let zones = shipping_zones::table
+        .order(shipping_zones::name.asc())
+        .load::<ShippingZone>(conn)?;
+
+    let mut methods = shipping_methods::table
+        .order(shipping_methods::amount.asc())
+        .load::<ShippingMethod>(conn)?;
+
+    let zones = zones.into_iter()
+        .map(|zone| {
+            let (zone_methods, other_methods) = methods.drain(..).partition(|method| method.zone_id == zone.id);
+            methods = other_methods;
+
+            ShippingZoneDetails { zone, methods: zone_methods }
+        })
+        .collect();
+
+    Ok(zones)
+}
+
+pub(crate) fn db_count_shipping_zones(
+    conn: &mut PgConnection,
+) -> Result<i64, Error> {
+    shipping_zones::table.count().get_result::<i64>(conn)
+}
+
+pub(crate) fn db_get_shipping_zone_for_country(
+    conn: &mut PgConnection,
+    country: String,
+) -> Result<Option<ShippingZone>, Error> {
+    let zone = shipping_zones::table
+        .filter(shipping_zones::countries.contains(vec![Some(country)]))
+        .order(shipping_zones::created_at.asc())
+        .first::<ShippingZone>(conn)
+        .optional()?;
+
+    Ok(zone)
+}
+
+// cheapest first, stripe shows them in the order they are sent
+pub(crate) fn db_get_active_shipping_methods(
+    conn: &mut PgConnection,
+    zone: String,
+) -> Result<Vec<ShippingMethod>, Error> {
+    let methods = shipping_methods::table
+        .filter(shipping_methods::zone_id.eq(zone))
+        .filter(shipping_methods::active.eq(true))
+        .order(shipping_methods::amount.asc())
+        .load::<ShippingMethod>(conn)?;
+
+    Ok(methods)
+}
+
+pub(crate) fn db_create_shipping_zone(
+    conn: &mut PgConnection,
+    new_zone: NewShippingZone,
+) -> Result<ShippingZone, Error> {
+    let zone = diesel::insert_into(shipping_zones::table)
+        .values(&new_zone)
+        .get_result::<ShippingZone>(conn)?;
+
+    Ok(zone)
+}
+
+pub(crate) fn db_update_shipping_zone(
+    conn: &mut PgConnection,
+    zone_id: String,
+    new_zone: NewShippingZone,
+) -> Result<ShippingZone, Error> {
+    let zone = diesel::update(shipping_zones::table.find(zone_id))
+        .set(&new_zone)
+        .get_result::<ShippingZone>(conn)?;
+
+    Ok(zone)
+}
+
+pub(crate) fn db_delete_shipping_zone(
+    conn: &mut PgConnection,
+    zone_id: String,
+) -> Result<usize, Error> {
+    let deleted_zone = diesel::delete(shipping_zones::table.find(zone_id))
+        .execute(conn)?;
+
+    Ok(deleted_zone)
+}
+
+pub(crate) fn db_create_shipping_method(
+    conn: &mut PgConnection,
+    new_method: NewShippingMethod,
+) -> Result<Result<ShippingMethod, ShippingError>, Error> {
+    write_shipping_method(conn, |conn| {
+        diesel::insert_into(shipping_methods::table)
+            .values(&new_method)
+            .get_result::<ShippingMethod>(conn)
+    })
+}
+
+pub(crate) fn db_update_shipping_method(
+    conn: &mut PgConnection,
+    method_id: String,
+    new_method: NewShippingMethod,
+) -> Result<Result<ShippingMethod, ShippingError>, Error> {
+    write_shipping_method(conn, |conn| {
+        diesel::update(shipping_methods::table.find(method_id))
+            .set(&new_method)
+            .get_result::<ShippingMethod>(conn)
+    })
+}
+
+// the write is undone when it leaves the zone with more active methods than a checkout session takes. the zone is
+// locked before counting so two methods added at the same time can't both slip in
+fn write_shipping_method(
+    conn: &mut PgConnection,
+    write: impl FnOnce(&mut PgConnection) -> Result<ShippingMethod, Error>,
+) -> Result<Result<ShippingMethod, ShippingError>, Error> {
+    let mut full_zone = None;
+    let written = conn.transaction(|conn| {
+        let method = write(conn)?;
+
+        shipping_zones::table
+            .find(&method.zone_id)
+            .for_update()
+            .execute(conn)?;
+        let active_methods = shipping_methods::table
+            .filter(shipping_methods::zone_id.eq(&method.zone_id))
+            .filter(shipping_methods::active.eq(true))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        if active_methods > MAX_ACTIVE_METHODS {
+            full_zone = Some(method.zone_id);
+            return Err(Error::RollbackTransaction);
+        }
+
+        Ok(method)
+    });
+
+    match (written, full_zone) {
+        (Ok(method), _) => Ok(Ok(method)),
+        (Err(Error::RollbackTransaction), Some(zone_id)) => Ok(Err(ShippingError::TooManyMethods { zone_id, max: MAX_ACTIVE_METHODS })),
+        (Err(e), _) => Err(e),
+    }
+}
+
+pub(crate) fn db_delete_shipping_method(
+    conn: &mut PgConnection,
+    method_id: String,
+) -> Result<usize, Error> {
+    let deleted_method = diesel::delete(shipping_methods::table.find(method_id))
+        .execute(conn)?;
+
+    Ok(deleted_method)
+}
\ No newline at end of file
@@ -1,30 +1,296 @@
 use diesel::{PgConnection, RunQueryDsl, QueryDsl, ExpressionMethods, result::Error};
 use diesel::prelude::*;
+use chrono::NaiveDateTime;
 
 
-use crate::{models::user::User, schema::users::dsl::*};
+use bigdecimal::BigDecimal;
 
-pub(crate) fn db_create_user (
+use crate::{models::{order::Order, pagination::{Page, PageQuery}, user::{ErasureError, ProfileChanges, User, UserExport, UserOverview}}, schema::{abandoned_carts, carts, email_outbox, orders, user_addresses}, schema::users::dsl::*};
+
+use super::{addresses::db_get_user_addresses, carts::db_get_cart_items_by_user_id, credit::db_get_credit_entries, orders::db_get_expanded_orders_by_user_id, returns::db_get_returns_by_user_id};
+
+/// insert the user or bring its email up to date, it is called on every login and an erased user logging in
+/// again starts over with an empty account. the roles only seed a new user, after that they are managed through
+/// the admin role endpoints and a login doesn't undo a grant or a revoke
+pub(crate) fn db_upsert_user (
     conn: &mut PgConnection,
-    new_user: User,
+    user_id: String,
+    new_email: String,
+    new_roles: Option<Vec<String>>,
 ) -> Result<User, Error> {
     let user = diesel::insert_into(users)
-        .values(&new_user)
-        .on_conflict_do_nothing()
+        .values((
+            id.eq(&user_id),
+            email.eq(&new_email),
+            roles.eq(new_roles.unwrap_or_default()),
+            last_seen_at.eq(diesel::dsl::now),
+        ))
+        .on_conflict(id)
+        .do_update()
+        .set((email.eq(&new_email), last_seen_at.eq(diesel::dsl::now), erased_at.eq(None::<NaiveDateTime>)))
         .get_result::<User>(conn)?;
 
     Ok(user)
 }
 
-pub(crate) fn db_update_user (
+/// link the stripe customer unless the user already has one, returns the user as stored
+pub(crate) fn db_link_stripe_customer (
     conn: &mut PgConnection,
     user_id: String,
     stripe: String,
-    new_roles: Vec<String>,
 ) -> Result<User, Error> {
+    diesel::update(users.find(&user_id))
+        .filter(stripe_id.is_null())
+        .set(stripe_id.eq(stripe))
+        .execute(conn)?;
+
+    users.find(user_id).first::<User>(conn)
+}
+
+pub(crate) fn db_update_user_email_by_stripe_id (
+    conn: &mut PgConnection,
+    stripe_customer_id: String,
+    new_email: String,
+) -> Result<usize, Error> {
+    let updated = diesel::update(users.filter(stripe_id.eq(stripe_customer_id)))
+        .set(email.eq(new_email))
+        .execute(conn)?;
+
+    Ok(updated)
+}
+
+pub(crate) fn db_unlink_stripe_customer (
+    conn: &mut PgConnection,
+    stripe_customer_id: String,
+) -> Result<usize, Error> {
+    let unlinked = diesel::update(users.filter(stripe_id.eq(stripe_customer_id)))
+        .set(stripe_id.eq(None::<String>))
+        .execute(conn)?;
+
+    Ok(unlinked)
+}
+
+pub(crate) fn db_update_user_profile (
+    conn: &mut PgConnection,
+    user_id: String,
+    changes: ProfileChanges,
+) -> Result<Option<User>, Error> {
+    // an empty update would be a syntax error, it just reads the user back
+    if changes.display_name.is_none() && changes.phone.is_none() && changes.marketing_opt_in.is_none() {
+        return db_get_user(conn, user_id);
+    }
+
     let user = diesel::update(users.find(user_id))
-        .set((stripe_id.eq(stripe), roles.eq(new_roles)))
-        .get_result::<User>(conn)?;
+        .set(changes)
+        .get_result::<User>(conn)
+        .optional()?;
+
+    Ok(user)
+}
+
+/// record that the user was active now
+pub(crate) fn db_touch_user (
+    conn: &mut PgConnection,
+    user_id: String,
+) -> Result<Option<User>, Error> {
+    let user = diesel::update(users.find(user_id))
+        .set(last_seen_at.eq(diesel::dsl::now))
+        .get_result::<User>(conn)
+        .optional()?;
+
+    Ok(user)
+}
+
+pub(crate) fn db_export_user (
+    conn: &mut PgConnection,
+    user_id: String,
+) -> Result<Option<UserExport>, Error> {
+    let profile = match db_get_user(conn, user_id.clone())? {
+        Some(profile) => profile,
+        None => return Ok(None),
+    };
+
+    Ok(Some(UserExport {
+        profile,
+        addresses: db_get_user_addresses(conn, user_id.clone())?,
+        cart: db_get_cart_items_by_user_id(conn, user_id.clone())?.unwrap_or_default(),
+        orders: db_get_expanded_orders_by_user_id(conn, user_id.clone())?.unwrap_or_default(),
+        returns: db_get_returns_by_user_id(conn, user_id.clone())?,
+        store_credit: db_get_credit_entries(conn, user_id)?,
+        exported_at: chrono::Utc::now().naive_utc(),
+    }))
+}
+
+/// erase the personal data of a user, orders are kept for accounting with the recipient and address
+/// cleared, only the country stays for tax reporting. returns the user as it was before
+pub(crate) fn db_erase_user (
+    conn: &mut PgConnection,
+    user_id: String,
+) -> Result<Option<Result<User, ErasureError>>, Error> {
+    conn.transaction(|conn| {
+        let user = match users.find(&user_id).for_update().first::<User>(conn).optional()? {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+
+        let open_orders = orders::table
+            .filter(orders::user_id.eq(&user_id))
+            .filter(orders::status.eq_any(["processing", "partially_shipped", "canceling"]))
+            .select(orders::id)
+            .load::<String>(conn)?;
+        if !open_orders.is_empty() {
+            return Ok(Some(Err(ErasureError::OpenOrders { orders: open_orders })));
+        }
+
+        let user_orders = orders::table
+            .filter(orders::user_id.eq(&user_id))
+            .select(orders::id)
+            .load::<String>(conn)?;
+
+        diesel::update(orders::table.filter(orders::user_id.eq(&user_id)))
+            .set((
+                orders::name.eq(""),
+                orders::phone.eq(None::<String>),
+                orders::address_line1.eq(None::<String>),
+                orders::address_line2.eq(None::<String>),
+                orders::address_city.eq(None::<String>),
+                orders::address_state.eq(None::<String>),
+                orders::address_postal_code.eq(None::<String>),
+            ))
+            .execute(conn)?;
+
+        // sent emails keep a copy of the address and the name
+        diesel::delete(email_outbox::table.filter(
+            email_outbox::order_id.eq_any(user_orders).or(email_outbox::recipient.eq(&user.email))
+        ))
+        .execute(conn)?;
+
+        diesel::delete(carts::table.filter(carts::user_id.eq(&user_id))).execute(conn)?;
+        diesel::delete(abandoned_carts::table.filter(abandoned_carts::user_id.eq(&user_id))).execute(conn)?;
+        diesel::delete(user_addresses::table.filter(user_addresses::user_id.eq(&user_id))).execute(conn)?;
+
+        diesel::update(users.find(&user_id))
+            .set((
+                email.eq(""),
+                roles.eq(Vec::<String>::new()),
+                stripe_id.eq(None::<String>),
+                display_name.eq(None::<String>),
+                phone.eq(None::<String>),
+                marketing_opt_in.eq(false),
+                erased_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        Ok(Some(Ok(user)))
+    })
+}
+
+pub(crate) fn db_search_users (
+    conn: &mut PgConnection,
+    search: Option<String>,
+    query: &PageQuery,
+) -> Result<Page<User>, Error> {
+    let pattern = search
+        .map(|search| search.trim().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+        .filter(|search| !search.is_empty())
+        .map(|search| format!("%{}%", search));
+
+    let filtered = || {
+        let mut filtered = users.into_boxed();
+        if let Some(pattern) = &pattern {
+            filtered = filtered.filter(email.ilike(pattern.clone()).or(id.ilike(pattern.clone())));
+        }
+        filtered
+    };
+
+    let total = filtered().count().get_result::<i64>(conn)?;
+
+    let found = filtered()
+        .order(created_at.desc())
+        .limit(query.per_page())
+        .offset(query.offset())
+        .load::<User>(conn)?;
+
+    Ok(Page::new(found, query, total))
+}
+
+pub(crate) fn db_get_user_overview (
+    conn: &mut PgConnection,
+    user_id: String,
+) -> Result<Option<UserOverview>, Error> {
+    let user = match db_get_user(conn, user_id.clone())? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    let user_orders = orders::table
+        .filter(orders::user_id.eq(user_id))
+        .filter(orders::status.ne_all(["canceled", "canceling"]))
+        .load::<Order>(conn)?;
+
+    // what was paid with the card and store credit and not given back
+    let lifetime_spend = user_orders.iter()
+        .map(|order| order.refundable())
+        .sum::<BigDecimal>();
+
+    Ok(Some(UserOverview {
+        user,
+        order_count: user_orders.len() as i64,
+        lifetime_spend: lifetime_spend.with_scale(2),
+    }))
+}
+
+/// add or remove one role of the user, roles they already have or lack are left as they are
+pub(crate) fn db_set_user_role (
+    conn: &mut PgConnection,
+    user_id: String,
+    role: String,
+    granted: bool,
+) -> Result<Option<User>, Error> {
+    conn.transaction(|conn| {
+        let current = match users.find(&user_id).select(roles).for_update().first::<Option<Vec<Option<String>>>>(conn).optional()? {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+
+        let mut new_roles = current.unwrap_or_default().into_iter()
+            .flatten()
+            .filter(|current_role| *current_role != role)
+            .collect::<Vec<String>>();
+        if granted {
+            new_roles.push(role);
+        }
+
+        let user = diesel::update(users.find(user_id))
+            .set(roles.eq(new_roles))
+            .get_result::<User>(conn)?;
+
+        Ok(Some(user))
+    })
+}
+
+/// tokens of a disabled user are refused until the account is enabled again
+pub(crate) fn db_disable_user (
+    conn: &mut PgConnection,
+    user_id: String,
+    reason: Option<String>,
+) -> Result<Option<User>, Error> {
+    let user = diesel::update(users.find(user_id))
+        .set((disabled_at.eq(diesel::dsl::now), disabled_reason.eq(reason)))
+        .get_result::<User>(conn)
+        .optional()?;
+
+    Ok(user)
+}
+
+pub(crate) fn db_enable_user (
+    conn: &mut PgConnection,
+    user_id: String,
+) -> Result<Option<User>, Error> {
+    let user = diesel::update(users.find(user_id))
+        .set((disabled_at.eq(None::<NaiveDateTime>), disabled_reason.eq(None::<String>)))
+        .get_result::<User>(conn)
+        .optional()?;
 
     Ok(user)
 }
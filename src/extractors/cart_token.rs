@@ -0,0 +1,73 @@
+use std::future::{ready, Ready};
+
+use actix_web::{cookie::{Cookie, SameSite}, error, Error, FromRequest, HttpRequest};
+use chrono::NaiveDateTime;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+pub(crate) const CART_TOKEN_HEADER: &str = "X-Cart-Token";
+pub(crate) const CART_TOKEN_COOKIE: &str = "cart_token";
+
+/// signed token identifying an anonymous shopper's guest cart
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct CartToken {
+    pub(crate) sub: String,
+    exp: i64,
+}
+
+impl CartToken {
+    /// sign a token for a guest cart that expires together with the cart
+    pub(crate) fn issue(guest_cart_id: String, expires_at: NaiveDateTime) -> Result<String, jsonwebtoken::errors::Error> {
+        let claims = CartToken {
+            sub: guest_cart_id,
+            exp: expires_at.and_utc().timestamp(),
+        };
+
+        encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(cart_token_secret().as_bytes()))
+    }
+
+    /// cookie carrying the token for clients that do not send the header
+    pub(crate) fn cookie(token: String, max_age: chrono::Duration) -> Cookie<'static> {
+        Cookie::build(CART_TOKEN_COOKIE, token)
+            .path("/api/cart")
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::None)
+            .max_age(actix_web::cookie::time::Duration::seconds(max_age.num_seconds()))
+            .finish()
+    }
+}
+
+impl FromRequest for CartToken {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(
+        req: &HttpRequest,
+        _payload: &mut actix_web::dev::Payload
+    ) -> Self::Future {
+        // prefer the header and fall back to the cookie
+        let token = req.headers()
+            .get(CART_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+            .or_else(|| req.cookie(CART_TOKEN_COOKIE).map(|cookie| cookie.value().to_string()));
+
+        let token = match token {
+            Some(token) => token,
+            None => return ready(Err(error::ErrorUnauthorized("Missing cart token"))),
+        };
+
+        let decoded_token = decode::<CartToken>(
+            &token,
+            &DecodingKey::from_secret(cart_token_secret().as_bytes()),
+            &Validation::new(Algorithm::HS256),
+        );
+
+        ready(decoded_token.map(|token| token.claims).map_err(error::ErrorUnauthorized))
+    }
+}
+
+fn cart_token_secret() -> String {
+    std::env::var("CART_TOKEN_SECRET").expect("CART_TOKEN_SECRET should be set")
+}
\ No newline at end of file
@@ -1,31 +1,101 @@
-use std::{collections::HashSet, pin::Pin, future::Future};
+use std::{collections::{HashMap, HashSet}, pin::Pin, future::Future};
 
-use actix_web::{FromRequest, Error};
+use actix_web::{error, web, FromRequest, Error};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
-use cached::proc_macro::cached;
-use jsonwebtoken::{jwk::{self, AlgorithmParameters}, decode_header, DecodingKey, Validation, decode, TokenData};
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeMap, Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+use crate::{database::{api_keys::db_use_api_key, users::db_get_user}, models::{api_key::{hash_api_key, ApiKey}, dbpool::PgPool, user::User}};
+
+pub(crate) const API_KEY_HEADER: &str = "X-Api-Key";
+
+use super::{permissions::{role_permissions, ALL_PERMISSIONS}, verifier::Authenticator};
+
+#[derive(Debug, Deserialize)]
+#[serde(from = "RawClaims")]
 pub(crate) struct Claims {
-    #[serde(rename = "https://localhost:8080/roles")]
     roles: Option<HashSet<String>>,
+    permissions: HashSet<String>,
     pub(crate) sub: String,
+    api_key: bool,
+}
+
+// the token as auth0 sends it, roles sit under a namespaced custom claim and
+// permissions come from the rbac `permissions` claim or the granted scopes
+#[derive(Debug, Deserialize)]
+struct RawClaims {
+    sub: String,
+    permissions: Option<Vec<String>>,
+    scope: Option<String>,
+    #[serde(flatten)]
+    custom: HashMap<String, serde_json::Value>,
+}
+
+impl From<RawClaims> for Claims {
+    fn from(raw: RawClaims) -> Self {
+        let roles = raw.custom.get(&roles_claim())
+            .and_then(|roles| serde_json::from_value::<HashSet<String>>(roles.clone()).ok());
+
+        // only the permissions this api knows about count, auth0 adds scopes like `openid` too.
+        // the roles are granted once the user is looked up, see `FromRequest`
+        let permissions = raw.permissions.unwrap_or_default().into_iter()
+            .chain(raw.scope.unwrap_or_default().split_whitespace().map(String::from))
+            .filter(|permission| ALL_PERMISSIONS.contains(&permission.as_str()))
+            .collect();
+
+        Claims { roles, permissions, sub: raw.sub, api_key: false }
+    }
+}
+
+// an api key acts as its own principal, holding the permissions it was scoped to as long as its creator still
+// holds them too
+impl From<(ApiKey, User)> for Claims {
+    fn from((api_key, creator): (ApiKey, User)) -> Self {
+        let held = creator.roles.unwrap_or_default().into_iter()
+            .flatten()
+            .flat_map(|role| role_permissions(&role).iter().copied())
+            .collect::<HashSet<&str>>();
+
+        let permissions = api_key.scopes.into_iter()
+            .flatten()
+            .filter(|permission| held.contains(permission.as_str()))
+            .collect();
+
+        Claims { roles: None, permissions, sub: api_key.id, api_key: true }
+    }
+}
+
+// echoed back by `/api/user/index` in the shape of the token
+impl Serialize for Claims {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry(&roles_claim(), &self.roles)?;
+        map.serialize_entry("sub", &self.sub)?;
+        map.end()
+    }
 }
 
 impl Claims {
-    pub fn validate_roles(&self, required_permissions: &HashSet<String>) -> bool {
-        log::info!("Validating permissions: {:?} against {:?}", self.roles, required_permissions);
-        if let Some(roles) = &self.roles {
-            if !roles.is_superset(required_permissions) {
-                false
-            } else {
-                true
-            }
-        } else {
-            false
+    /// add the permissions of the user's roles on top of the ones the token carries
+    fn grant_roles(&mut self, roles: impl Iterator<Item = String>) {
+        for role in roles {
+            self.permissions.extend(role_permissions(&role).iter().map(|permission| permission.to_string()));
         }
     }
+
+    pub(crate) fn is_api_key(&self) -> bool {
+        self.api_key
+    }
+
+    pub(crate) fn has_permission(&self, permission: &str) -> bool {
+        log::info!("Checking permission {} against {:?}", permission, self.permissions);
+        self.permissions.contains(permission)
+    }
+}
+
+/// the custom claim carrying the auth0 roles, namespaced by `AUTH0_CLAIM_NAMESPACE`
+fn roles_claim() -> String {
+    let namespace = std::env::var("AUTH0_CLAIM_NAMESPACE").unwrap_or("https://localhost:8080".to_string());
+    format!("{}/roles", namespace.trim_end_matches('/'))
 }
 
 impl FromRequest for Claims {
@@ -36,54 +106,70 @@ impl FromRequest for Claims {
         req: &actix_web::HttpRequest, 
         _payload: &mut actix_web::dev::Payload
     ) -> Self::Future {
+        let api_key = req.headers()
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
         let extractor = BearerAuth::extract(req);
+        let authenticator = req.app_data::<web::Data<Authenticator>>().cloned();
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
         Box::pin(async move {
+            let pool = pool.ok_or_else(|| error::ErrorInternalServerError("no database configured"))?;
+
+            // integrations send an api key instead of a bearer token
+            if let Some(api_key) = api_key {
+                let (api_key, creator) = web::block(move || {
+                    let mut conn = pool.get().unwrap();
+                    let api_key = match db_use_api_key(&mut conn, hash_api_key(&api_key))? {
+                        Some(api_key) => api_key,
+                        None => return Ok(None),
+                    };
+                    let creator = db_get_user(&mut conn, api_key.created_by.clone())?;
+
+                    Ok::<_, diesel::result::Error>(Some((api_key, creator)))
+                })
+                .await?
+                .map_err(error::ErrorInternalServerError)?
+                .ok_or_else(|| error::ErrorUnauthorized("Invalid, expired or revoked API key"))?;
+
+                // a key stops working with the account of the user who created it
+                return match creator {
+                    Some(creator) if creator.disabled_at.is_none() => Ok(Claims::from((api_key, creator))),
+                    _ => Err(error::ErrorForbidden("The account that created this API key is disabled")),
+                };
+            }
+
             let credientials = extractor.await?;
-            let token = credientials.token();
-            let token = verify_jwt(token).await?;
-            Ok(token.claims)
-        })
-    }
-}
+            let authenticator = authenticator.ok_or_else(|| error::ErrorInternalServerError("no authenticator configured"))?;
+            let mut claims = authenticator.verify(credientials.token())
+                .await
+                .map_err(|e| error::ErrorUnauthorized(e.to_string()))?;
 
-/// verify a jwt token
-pub(crate) async fn verify_jwt(token: &str) -> Result<TokenData<Claims>, Box<dyn std::error::Error>> {
-
-    // get the jwks from auth0
-    let jwks: jwk::JwkSet = get_jwks().await?;
-
-    // decode the token header
-    let header = decode_header(token)?;
-
-    // get the kid from the header
-    let kid = match header.kid {
-        Some(k) => k,
-        None => return Err("Token doesn't have a `kid` header field".into()),
-    };
-
-    // find the jwk that matches the kid
-    if let Some(j) = jwks.find(&kid) {
-        // make sure the jwk is a RSA
-        match &j.algorithm {
-            AlgorithmParameters::RSA(rsa) => {
-                let decoding_key = DecodingKey::from_rsa_components(&rsa.n, &rsa.e).unwrap();
-                let validation = Validation::new(j.common.algorithm.unwrap());
-                let decoded_token =
-                    decode::<Claims>(token, &decoding_key, &validation)?;
-                return Ok(decoded_token);
+            // tokens stay valid until they expire, so a disabled account is checked on every request
+            let sub = claims.sub.clone();
+            let user = web::block(move || {
+                let mut conn = pool.get().unwrap();
+                db_get_user(&mut conn, sub)
+            })
+            .await?
+            .map_err(error::ErrorInternalServerError)?;
+
+            // the roles stored on the user are the ones that count, so revoking a role takes effect even while
+            // auth0 still puts it in the token. a user that was never provisioned only has the token's roles
+            match user {
+                Some(user) => {
+                    if user.disabled_at.is_some() {
+                        return Err(error::ErrorForbidden("This account is disabled"));
+                    }
+                    claims.grant_roles(user.roles.unwrap_or_default().into_iter().flatten());
+                },
+                None => {
+                    let roles = claims.roles.clone().unwrap_or_default();
+                    claims.grant_roles(roles.into_iter());
+                },
             }
-            _ => unreachable!("this should be a RSA"),
-        }
-    } else {
-        return Err("No matching JWK found for the given kid".into());
-    }
-}
 
-/// get and cache jwks from auth0 website
-#[cached(size=1, time = 1200, result = true)]
-async fn get_jwks() -> Result<jwk::JwkSet, Box<dyn std::error::Error>> {
-    let uri = std::env::var("AUTH0_JWKS").expect("AUTH0_JWKS should be set");
-    let res = reqwest::get(uri).await?.text().await?;
-    let jwks: jwk::JwkSet = serde_json::from_str(&res)?;
-    Ok(jwks)
+            Ok(claims)
+        })
+    }
 }
\ No newline at end of file
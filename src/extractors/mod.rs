@@ -1 +1,4 @@
-pub mod claims;
\ No newline at end of file
+pub mod cart_token;
+pub mod claims;
+pub mod permissions;
+pub mod verifier;
\ No newline at end of file
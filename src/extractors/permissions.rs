@@ -0,0 +1,102 @@
+use std::{future::Future, marker::PhantomData, ops::Deref, pin::Pin};
+
+use actix_web::{error, Error, FromRequest, HttpRequest};
+use super::claims::Claims;
+
+/// a named permission a token has to carry, directly or through one of its roles
+pub(crate) trait Permission {
+    const NAME: &'static str;
+}
+
+/// create, edit and archive products, promotions and shipping methods
+pub(crate) struct ProductsWrite;
+/// look up any order, its shipments and returns
+pub(crate) struct OrdersRead;
+/// create, edit and delete orders
+pub(crate) struct OrdersWrite;
+/// ship orders and take returned goods back in
+pub(crate) struct OrdersFulfill;
+/// decide on returns and pay money back
+pub(crate) struct OrdersRefund;
+/// manage user accounts, their carts and store credit
+pub(crate) struct UsersManage;
+
+impl Permission for ProductsWrite {
+    const NAME: &'static str = "products:write";
+}
+
+impl Permission for OrdersRead {
+    const NAME: &'static str = "orders:read";
+}
+
+impl Permission for OrdersWrite {
+    const NAME: &'static str = "orders:write";
+}
+
+impl Permission for OrdersFulfill {
+    const NAME: &'static str = "orders:fulfill";
+}
+
+impl Permission for OrdersRefund {
+    const NAME: &'static str = "orders:refund";
+}
+
+impl Permission for UsersManage {
+    const NAME: &'static str = "users:manage";
+}
+
+pub(crate) const ALL_PERMISSIONS: [&str; 6] = [
+    ProductsWrite::NAME,
+    OrdersRead::NAME,
+    OrdersWrite::NAME,
+    OrdersFulfill::NAME,
+    OrdersRefund::NAME,
+    UsersManage::NAME,
+];
+
+/// the roles staff can be granted
+pub(crate) const ROLES: [&str; 2] = ["admin", "fulfillment"];
+
+/// what each auth0 role is allowed to do
+pub(crate) fn role_permissions(role: &str) -> &'static [&'static str] {
+    match role {
+        "admin" => &ALL_PERMISSIONS,
+        "fulfillment" => &[OrdersRead::NAME, OrdersFulfill::NAME],
+        _ => &[],
+    }
+}
+
+/// claims of a token holding the permission `P`, a missing or invalid token is a 401 and a missing permission a 403
+pub(crate) struct RequirePermission<P: Permission> {
+    claims: Claims,
+    permission: PhantomData<P>,
+}
+
+impl<P: Permission> Deref for RequirePermission<P> {
+    type Target = Claims;
+
+    fn deref(&self) -> &Self::Target {
+        &self.claims
+    }
+}
+
+impl<P: Permission + 'static> FromRequest for RequirePermission<P> {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(
+        req: &HttpRequest,
+        _payload: &mut actix_web::dev::Payload
+    ) -> Self::Future {
+        let extractor = Claims::extract(req);
+        Box::pin(async move {
+            let claims = extractor.await?;
+
+            if !claims.has_permission(P::NAME) {
+                return Err(error::ErrorForbidden(format!("the {} permission is required", P::NAME)));
+            }
+
+            Ok(RequirePermission { claims, permission: PhantomData })
+        })
+    }
+}
\ No newline at end of file
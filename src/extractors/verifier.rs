@@ -0,0 +1,147 @@
+use std::{error::Error, future::Future, pin::Pin};
+
+use cached::proc_macro::cached;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, DecodingKey, Header, Validation};
+
+use super::claims::Claims;
+
+type KeyFuture<'a> = Pin<Box<dyn Future<Output = Result<DecodingKey, Box<dyn Error>>> + 'a>>;
+
+/// where the keys that sign access tokens come from
+pub(crate) trait JwtVerifier: Send + Sync {
+    /// the key that should have signed a token with this header
+    fn decoding_key<'a>(&'a self, header: &'a Header) -> KeyFuture<'a>;
+}
+
+/// the jwks an auth0 tenant publishes, refetched every 20 minutes
+pub(crate) struct JwksUrl {
+    uri: String,
+}
+
+impl JwksUrl {
+    pub(crate) fn new(uri: String) -> Self {
+        JwksUrl { uri }
+    }
+}
+
+impl JwtVerifier for JwksUrl {
+    fn decoding_key<'a>(&'a self, header: &'a Header) -> KeyFuture<'a> {
+        Box::pin(async move {
+            let jwks = get_jwks(self.uri.clone()).await?;
+            find_key(&jwks, header)
+        })
+    }
+}
+
+/// a jwks read from disk once at startup, for self-hosted issuers
+pub(crate) struct JwksFile {
+    jwks: JwkSet,
+}
+
+impl JwksFile {
+    pub(crate) fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let jwks = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(JwksFile { jwks })
+    }
+}
+
+impl JwtVerifier for JwksFile {
+    fn decoding_key<'a>(&'a self, header: &'a Header) -> KeyFuture<'a> {
+        Box::pin(async move { find_key(&self.jwks, header) })
+    }
+}
+
+/// a single shared secret or public key, tokens don't need a `kid`
+pub(crate) struct StaticKey {
+    key: DecodingKey,
+}
+
+impl StaticKey {
+    pub(crate) fn hmac(secret: &[u8]) -> Self {
+        StaticKey { key: DecodingKey::from_secret(secret) }
+    }
+
+    pub(crate) fn ec_pem(pem: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(StaticKey { key: DecodingKey::from_ec_pem(pem)? })
+    }
+}
+
+impl JwtVerifier for StaticKey {
+    fn decoding_key<'a>(&'a self, _header: &'a Header) -> KeyFuture<'a> {
+        Box::pin(async move { Ok(self.key.clone()) })
+    }
+}
+
+/// find the key matching the `kid` of the token, a key pinned to an algorithm only verifies that algorithm
+fn find_key(jwks: &JwkSet, header: &Header) -> Result<DecodingKey, Box<dyn Error>> {
+    let kid = header.kid.as_ref().ok_or("Token doesn't have a `kid` header field")?;
+    let jwk = jwks.find(kid).ok_or("No matching JWK found for the given kid")?;
+
+    if let Some(algorithm) = jwk.common.algorithm {
+        if algorithm != header.alg {
+            return Err(format!("The JWK for this kid only verifies {:?}", algorithm).into());
+        }
+    }
+
+    Ok(DecodingKey::from_jwk(jwk)?)
+}
+
+/// get and cache jwks from auth0 website
+#[cached(size=1, time = 1200, result = true)]
+async fn get_jwks(uri: String) -> Result<JwkSet, Box<dyn Error>> {
+    let res = reqwest::get(uri).await?.text().await?;
+    let jwks: JwkSet = serde_json::from_str(&res)?;
+    Ok(jwks)
+}
+
+/// checks access tokens against the configured keys, audience and issuer
+pub(crate) struct Authenticator {
+    verifier: Box<dyn JwtVerifier>,
+    audience: String,
+    issuer: String,
+    leeway: u64,
+}
+
+impl Authenticator {
+    pub(crate) fn new(verifier: Box<dyn JwtVerifier>, audience: String, issuer: String, leeway: u64) -> Self {
+        Authenticator { verifier, audience, issuer, leeway }
+    }
+
+    /// `JWT_HMAC_SECRET` or `JWT_EC_PUBLIC_KEY` pick a static key, otherwise keys come from
+    /// `AUTH0_JWKS_FILE` or the `AUTH0_JWKS` url
+    pub(crate) fn from_env() -> Self {
+        let verifier: Box<dyn JwtVerifier> = if let Ok(secret) = std::env::var("JWT_HMAC_SECRET") {
+            Box::new(StaticKey::hmac(secret.as_bytes()))
+        } else if let Ok(path) = std::env::var("JWT_EC_PUBLIC_KEY") {
+            let pem = std::fs::read(path).expect("JWT_EC_PUBLIC_KEY should be a readable pem file");
+            Box::new(StaticKey::ec_pem(&pem).expect("JWT_EC_PUBLIC_KEY should be an EC public key"))
+        } else if let Ok(path) = std::env::var("AUTH0_JWKS_FILE") {
+            Box::new(JwksFile::load(&path).expect("AUTH0_JWKS_FILE should be a readable jwks file"))
+        } else {
+            Box::new(JwksUrl::new(std::env::var("AUTH0_JWKS").expect("AUTH0_JWKS should be set")))
+        };
+
+        let audience = std::env::var("AUTH0_AUDIENCE").expect("AUTH0_AUDIENCE should be set");
+        let issuer = std::env::var("AUTH0_ISSUER").expect("AUTH0_ISSUER should be set");
+        let leeway = std::env::var("AUTH0_LEEWAY")
+            .map(|leeway| leeway.parse().expect("AUTH0_LEEWAY should be a number of seconds"))
+            .unwrap_or(60);
+
+        Authenticator::new(verifier, audience, issuer, leeway)
+    }
+
+    /// verify the signature, expiry, audience and issuer of a token
+    pub(crate) async fn verify(&self, token: &str) -> Result<Claims, Box<dyn Error>> {
+        let header = decode_header(token)?;
+        let key = self.verifier.decoding_key(&header).await?;
+
+        // the key type has to match the algorithm, so an rsa or ec key can't be used as an hmac secret
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.audience]);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["exp", "aud", "iss", "sub"]);
+        validation.leeway = self.leeway;
+
+        Ok(decode::<Claims>(token, &key, &validation)?.claims)
+    }
+}
\ No newline at end of file
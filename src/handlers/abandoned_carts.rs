@@ -0,0 +1,81 @@
+use actix_web::{get, web, HttpResponse, Responder, Result, error, post};
+
+use crate::database::abandoned_carts::{db_get_abandoned_cart, db_get_abandoned_carts, db_mark_recovered};
+use crate::database::carts::db_merge_into_cart;
+use crate::extractors::claims::Claims;
+use crate::extractors::permissions::UsersManage;
+use crate::jobs::abandoned_carts::send_cart_recovery;
+use crate::middleware::permission_guard::PermissionGuard;
+use crate::models::abandoned_cart::{AbandonedCartQuery, AbandonedCartReport};
+use crate::models::dbpool::PgPool;
+
+// how many carts were abandoned and what they were worth
+#[get("/abandoned", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn get_abandoned_cart_report(
+    pool: web::Data<PgPool>,
+    query: web::Query<AbandonedCartQuery>,
+) -> Result<impl Responder> {
+    let abandoned_carts = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_get_abandoned_carts(&mut conn, query.since)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(AbandonedCartReport::new(abandoned_carts)))
+}
+
+// send the recovery email for an abandoned cart by hand
+#[post("/abandoned/{id}/recover", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn send_abandoned_cart_recovery(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let abandoned_cart = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        match db_get_abandoned_cart(&mut conn, id.into_inner())? {
+            Some(abandoned_cart) => send_cart_recovery(&mut conn, &abandoned_cart).map(Some),
+            None => Ok(None),
+        }
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match abandoned_cart {
+        Some(abandoned_cart) => Ok(HttpResponse::Ok().json(abandoned_cart)),
+        None => Ok(HttpResponse::NotFound().body("Abandoned cart not found")),
+    }
+}
+
+// target of the recovery link, puts the abandoned items back into the customer's cart
+#[post("/restore/{id}")]
+pub(crate) async fn restore_abandoned_cart(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        let abandoned_cart = match db_get_abandoned_cart(&mut conn, id.into_inner())? {
+            Some(abandoned_cart) if abandoned_cart.user_id == claims.sub => abandoned_cart,
+            _ => return Ok(None),
+        };
+
+        let cart_items = db_merge_into_cart(&mut conn, claims.sub, abandoned_cart.lines())?;
+        if abandoned_cart.recovered_at.is_none() {
+            db_mark_recovered(&mut conn, abandoned_cart.id)?;
+        }
+
+        Ok::<_, diesel::result::Error>(cart_items)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match cart_items {
+        Some(cart_items) => Ok(HttpResponse::Ok().json(cart_items)),
+        None => Ok(HttpResponse::NotFound().body("Abandoned cart not found")),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,127 @@
+use actix_web::{delete, error, get, post, put, web, HttpResponse, Responder, Result};
+
+use crate::database::addresses::{
+    db_create_user_address, db_delete_user_address, db_get_user_address, db_get_user_addresses,
+    db_update_user_address,
+};
+use crate::extractors::claims::Claims;
+use crate::models::address::{AddressValidationErrors, NewUserAddress, UserAddressPayload};
+use crate::models::dbpool::PgPool;
+
+// the address book of the logged in user
+#[get("/addresses")]
+pub(crate) async fn get_user_addresses(
+    pool: web::Data<PgPool>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let addresses = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_user_addresses(&mut conn, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(addresses))
+}
+
+#[get("/addresses/{id}")]
+pub(crate) async fn get_user_address(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let address = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_user_address(&mut conn, claims.sub, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match address {
+        Some(address) => Ok(HttpResponse::Ok().json(address)),
+        None => Ok(HttpResponse::NotFound().body("Address not found")),
+    }
+}
+
+#[post("/addresses")]
+pub(crate) async fn create_user_address(
+    pool: web::Data<PgPool>,
+    payload: web::Json<UserAddressPayload>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let payload = payload.into_inner();
+    let address = payload.address.normalize();
+    let errors = address.validate();
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(AddressValidationErrors { errors }));
+    }
+
+    let new_address = NewUserAddress::new(claims.sub, payload.label, address);
+    let address = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_create_user_address(
+            &mut conn,
+            new_address,
+            payload.default_shipping,
+            payload.default_billing,
+        )
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Created().json(address))
+}
+
+// replaces the whole address, the defaults only change when they are given
+#[put("/addresses/{id}")]
+pub(crate) async fn update_user_address(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    payload: web::Json<UserAddressPayload>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let payload = payload.into_inner();
+    let address = payload.address.normalize();
+    let errors = address.validate();
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(AddressValidationErrors { errors }));
+    }
+
+    let new_address = NewUserAddress::new(claims.sub, payload.label, address);
+    let address = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_update_user_address(
+            &mut conn,
+            id.into_inner(),
+            new_address,
+            payload.default_shipping,
+            payload.default_billing,
+        )
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match address {
+        Some(address) => Ok(HttpResponse::Ok().json(address)),
+        None => Ok(HttpResponse::NotFound().body("Address not found")),
+    }
+}
+
+#[delete("/addresses/{id}")]
+pub(crate) async fn delete_user_address(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let deleted = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_delete_user_address(&mut conn, claims.sub, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match deleted {
+        0 => Ok(HttpResponse::NotFound().body("Address not found")),
+        _ => Ok(HttpResponse::Ok().body("Address deleted")),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,67 @@
+use actix_web::{delete, error, get, post, web, HttpResponse, Responder, Result};
+
+use crate::database::api_keys::{db_create_api_key, db_get_api_keys, db_revoke_api_key};
+use crate::extractors::permissions::{RequirePermission, UsersManage};
+use crate::middleware::permission_guard::PermissionGuard;
+use crate::models::api_key::{ApiKeyRequest, CreatedApiKey, NewApiKey};
+use crate::models::dbpool::PgPool;
+
+#[get("/admin/api_keys", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn admin_get_api_keys(
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder> {
+    let keys = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_api_keys(&mut conn)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+// the key is returned once, only its hash is stored
+#[post("/admin/api_keys", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn admin_create_api_key(
+    pool: web::Data<PgPool>,
+    request: web::Json<ApiKeyRequest>,
+    staff: RequirePermission<UsersManage>,
+) -> Result<impl Responder> {
+    // a key minting keys would outlive its own revocation
+    if staff.is_api_key() {
+        return Err(error::ErrorForbidden("API keys can't create API keys"));
+    }
+
+    let request = request.into_inner();
+    if let Err(e) = request.validate(&staff) {
+        return Ok(HttpResponse::BadRequest().json(e));
+    }
+
+    let (key, new_api_key) = NewApiKey::generate(request, staff.sub.clone());
+    let api_key = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_create_api_key(&mut conn, new_api_key)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Created().json(CreatedApiKey { api_key, key }))
+}
+
+#[delete("/admin/api_keys/{id}", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn admin_revoke_api_key(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let api_key = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_revoke_api_key(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match api_key {
+        Some(api_key) => Ok(HttpResponse::Ok().json(api_key)),
+        None => Ok(HttpResponse::NotFound().body("API key not found")),
+    }
+}
\ No newline at end of file
@@ -1,9 +1,16 @@
+use std::collections::HashMap;
+
 use actix_web::{get, web, HttpResponse, Responder, Result, error, post, put};
+use diesel::PgConnection;
 
 use crate::extractors::claims::Claims;
-use crate::models::cart::{CartSubmit, NewCartItem};
+use crate::extractors::permissions::UsersManage;
+use crate::middleware::permission_guard::PermissionGuard;
+use crate::models::cart::{CartItem, CartItemSubmit, CartSubmit, CartSummary, CartValidationErrors, NewCartItem};
 use crate::models::dbpool::PgPool;
-use crate::database::carts::{db_get_cart_items_by_user_id, db_update_cart_item, db_create_cart_item, db_delete_cart_item, db_update_cart_item_from_cart};
+use crate::models::promotion::PromotionQuery;
+use crate::handlers::promotions::find_promotion;
+use crate::database::carts::{db_get_cart_items_by_user_id, db_get_cart_with_products, db_update_cart_item, db_create_cart_item, db_delete_cart_item, db_update_cart_item_from_cart, db_validate_cart_lines};
 
 
 #[get("")]
@@ -22,112 +29,217 @@ pub(crate) async fn get_cart_items(
     Ok(HttpResponse::Ok().json(cart_items))
 }
 
+// the cart priced against the current products so the client does not have to add it up
+#[get("/summary")]
+pub(crate) async fn get_cart_summary(
+    pool: web::Data<PgPool>,
+    query: web::Query<PromotionQuery>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let summary = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        let mut summary = CartSummary::new(db_get_cart_with_products(&mut conn, claims.sub)?);
+        if let Some(code) = query.into_inner().code {
+            summary.apply_promotion(find_promotion(&mut conn, code)?);
+        }
+
+        Ok::<_, diesel::result::Error>(summary)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(summary))
+}
+
 #[post("/add")]
 pub(crate) async fn add_to_cart(
     pool: web::Data<PgPool>,
-    new_cart: web::Json<NewCartItem>,
-    _claims: Claims,
+    new_cart: web::Json<CartItemSubmit>,
+    claims: Claims,
 ) ->  Result<impl Responder> {
-    let cart_item = new_cart.into_inner();
+    let cart_item = NewCartItem {
+        user_id: claims.sub,
+        product_id: new_cart.product_id.clone(),
+        quantity: new_cart.quantity,
+    };
 
     let cart_items = web::block(move || {
         let mut conn = pool.get().unwrap();
 
-        db_create_cart_item(&mut conn, cart_item)
+        let errors = db_validate_cart_lines(&mut conn, vec![(cart_item.product_id.clone(), cart_item.quantity)])?;
+        if !errors.is_empty() {
+            return Ok(Err(CartValidationErrors { errors }));
+        }
+
+        db_create_cart_item(&mut conn, cart_item).map(Ok)
     })
     .await?
     .map_err(error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().json(cart_items))
+    match cart_items {
+        Ok(cart_items) => Ok(HttpResponse::Ok().json(cart_items)),
+        Err(errors) => Ok(HttpResponse::BadRequest().json(errors)),
+    }
 }
 
 #[post("/update")]
 pub(crate) async fn update_cart_item(
     pool: web::Data<PgPool>,
-    new_cart: web::Json<NewCartItem>,
-    _claims: Claims,
+    new_cart: web::Json<CartItemSubmit>,
+    claims: Claims,
 ) ->  Result<impl Responder> {
+    let cart_item = NewCartItem {
+        user_id: claims.sub,
+        product_id: new_cart.product_id.clone(),
+        quantity: new_cart.quantity,
+    };
+
     let cart_items = web::block(move || {
         let mut conn = pool.get().unwrap();
-        
-        db_update_cart_item_from_cart(&mut conn, new_cart.into_inner())
+
+        let errors = db_validate_cart_lines(&mut conn, vec![(cart_item.product_id.clone(), cart_item.quantity)])?;
+        if !errors.is_empty() {
+            return Ok(Err(CartValidationErrors { errors }));
+        }
+
+        db_update_cart_item_from_cart(&mut conn, cart_item).map(Ok)
     })
     .await?
     .map_err(error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().json(cart_items))
+    match cart_items {
+        Ok(cart_items) => Ok(HttpResponse::Ok().json(cart_items)),
+        Err(errors) => Ok(HttpResponse::BadRequest().json(errors)),
+    }
 }
 
-// Determine which items to delete and which to update based on the current cart and the new cart
 #[put("/update_cart")]
 pub(crate) async fn update_cart(
     pool: web::Data<PgPool>,
     cart_submit: web::Json<CartSubmit>,
-    _claims: Claims,
+    claims: Claims,
 ) -> Result<impl Responder> {
-    let cart = cart_submit.cart.clone();
-    let user_id = cart_submit.user_id.clone();
+    let cart = cart_submit.into_inner().cart;
 
     let cart_items = web::block(move || {
         let mut conn = pool.get().unwrap();
 
-        let current_cart = db_get_cart_items_by_user_id(&mut conn, user_id.clone())?;
-
-        // Check if there is a current cart
-        match current_cart {
-            // If there is a current cart, update the cart with the submitted cart
-            Some(current_cart) => {
-                // Iterate through the current cart
-                // If the current cart item is in the submitted cart, update the quantity
-                // If the current cart item is not in the submitted cart, delete the item
-                for cart_item in current_cart.clone() {
-                    if cart.contains_key(&cart_item.product_id) {
-                        if cart_item.quantity == cart.get(&cart_item.product_id).unwrap().clone() {
-                            continue;
-                        }
-
-                        let new_quantity = cart.get(&cart_item.product_id).unwrap();
-
-                        db_update_cart_item(&mut conn, user_id.clone(), cart_item.product_id.clone(), *new_quantity)?;
-                    } else {
-                        db_delete_cart_item(&mut conn, user_id.clone(), cart_item.product_id.clone())?;
-                    }
-                }
+        replace_cart(&mut conn, claims.sub, cart)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
 
-                // Iterate through the submitted cart
-                // If the submitted cart item is not in the current cart, create a new cart item
-                for cart_item in cart {
-                    if current_cart.iter().any(|item| item.product_id == cart_item.0) {
+    match cart_items {
+        Ok(cart_items) => Ok(HttpResponse::Ok().json(cart_items)),
+        Err(errors) => Ok(HttpResponse::BadRequest().json(errors)),
+    }
+}
+
+// lets an admin look at the cart of any user
+#[get("/admin/{user_id}", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn admin_get_cart_items(
+    pool: web::Data<PgPool>,
+    user_id: web::Path<String>,
+) -> Result<impl Responder> {
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_get_cart_items_by_user_id(&mut conn, user_id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(cart_items))
+}
+
+// lets an admin replace the cart of any user
+#[put("/admin/{user_id}", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn admin_update_cart(
+    pool: web::Data<PgPool>,
+    user_id: web::Path<String>,
+    cart_submit: web::Json<CartSubmit>,
+) -> Result<impl Responder> {
+    let cart = cart_submit.into_inner().cart;
+
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        replace_cart(&mut conn, user_id.into_inner(), cart)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match cart_items {
+        Ok(cart_items) => Ok(HttpResponse::Ok().json(cart_items)),
+        Err(errors) => Ok(HttpResponse::BadRequest().json(errors)),
+    }
+}
+
+// Determine which items to delete and which to update based on the current cart and the new cart
+fn replace_cart(
+    conn: &mut PgConnection,
+    user_id: String,
+    cart: HashMap<String, i32>,
+) -> Result<Result<Option<Vec<CartItem>>, CartValidationErrors>, diesel::result::Error> {
+    // reject the whole cart if any line is invalid so it is never left half updated
+    let errors = db_validate_cart_lines(conn, cart.clone().into_iter().collect())?;
+    if !errors.is_empty() {
+        return Ok(Err(CartValidationErrors { errors }));
+    }
+
+    let current_cart = db_get_cart_items_by_user_id(conn, user_id.clone())?;
+
+    // Check if there is a current cart
+    match current_cart {
+        // If there is a current cart, update the cart with the submitted cart
+        Some(current_cart) => {
+            // Iterate through the current cart
+            // If the current cart item is in the submitted cart, update the quantity
+            // If the current cart item is not in the submitted cart, delete the item
+            for cart_item in current_cart.clone() {
+                if cart.contains_key(&cart_item.product_id) {
+                    if cart_item.quantity == cart.get(&cart_item.product_id).unwrap().clone() {
                         continue;
                     }
 
-                    let new_cart_item = NewCartItem {
-                        user_id: user_id.clone(),
-                        product_id: cart_item.0,
-                        quantity: cart_item.1,
-                    };
+                    let new_quantity = cart.get(&cart_item.product_id).unwrap();
 
-                    db_create_cart_item(&mut conn, new_cart_item)?;
+                    db_update_cart_item(conn, user_id.clone(), cart_item.product_id.clone(), *new_quantity)?;
+                } else {
+                    db_delete_cart_item(conn, user_id.clone(), cart_item.product_id.clone())?;
                 }
             }
-            // If there is no current cart, create a new cart with the submitted cart
-            None => {
-                for cart_item in cart {
-                    let new_cart_item = NewCartItem {
-                        user_id: user_id.clone(),
-                        product_id: cart_item.0,
-                        quantity: cart_item.1,
-                    };
-
-                    db_create_cart_item(&mut conn, new_cart_item)?;
+
+            // Iterate through the submitted cart
+            // If the submitted cart item is not in the current cart, create a new cart item
+            for cart_item in cart {
+                if current_cart.iter().any(|item| item.product_id == cart_item.0) {
+                    continue;
                 }
+
+                let new_cart_item = NewCartItem {
+                    user_id: user_id.clone(),
+                    product_id: cart_item.0,
+                    quantity: cart_item.1,
+                };
+
+                db_create_cart_item(conn, new_cart_item)?;
             }
         }
+        // If there is no current cart, create a new cart with the submitted cart
+        None => {
+            for cart_item in cart {
+                let new_cart_item = NewCartItem {
+                    user_id: user_id.clone(),
+                    product_id: cart_item.0,
+                    quantity: cart_item.1,
+                };
+
+                db_create_cart_item(conn, new_cart_item)?;
+            }
+        }
+    }
 
-        db_get_cart_items_by_user_id(&mut conn, cart_submit.user_id.clone())
-    })
-    .await?
-    .map_err(error::ErrorInternalServerError)?;
-
-    Ok(HttpResponse::Ok().json(cart_items))
+    db_get_cart_items_by_user_id(conn, user_id).map(Ok)
 }
\ No newline at end of file
@@ -1,12 +1,19 @@
+use std::{collections::HashMap, str::FromStr};
+
 use actix_web::{post, web, HttpResponse, Responder, Result, error};
-use stripe::{Client, CheckoutSession, Customer, Expandable, CheckoutSessionMode, CreateCheckoutSessionShippingAddressCollectionAllowedCountries, CheckoutSessionStatus};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use diesel::{Connection, PgConnection};
+use stripe::{Client, CheckoutSession, Customer, CustomerId, Expandable, CheckoutSessionMode, CreateCheckoutSessionShippingAddressCollectionAllowedCountries, CheckoutSessionStatus, Coupon, ShippingRate, CouponDuration, CreateCoupon, Currency, UpdateCustomer, UpdateCustomerShipping, UpdateCustomerShippingAddress};
 
-use crate::{models::{dbpool::PgPool, product}, database::{carts::{db_get_cart_items_by_user_id, db_delete_cart_items_by_user}, products::{db_get_product_by_id, db_update_product}, users::{db_get_user, db_user_stripe_to_user_id, db_user_id_to_stripe_id}}, extractors::claims::Claims, handlers::orders::create_order};
+use crate::{models::{address::ShippingAddress, dbpool::PgPool, cart::CartSummary, credit::{CreditEntry, CreditQuery}, order::NewOrder, promotion::{AppliedPromotion, PromotionError, PromotionQuery}, shipping::{ShippingError, ShippingQuery, ShippingQuote}}, database::{addresses::db_get_default_shipping_address, carts::{db_get_cart_items_by_user_id, db_delete_cart_items_by_user, db_get_cart_with_products}, credit::{db_assign_checkout_credit, db_attach_credit_to_session, db_cancel_credit_hold, db_hold_credit, db_release_checkout_credit}, orders::db_update_order, products::{db_get_product_by_id, db_restock_products, db_take_stock}, promotions::{db_redeem_promotion, db_release_promotion}, users::{db_get_user, db_user_stripe_to_user_id, db_user_id_to_stripe_id}}, extractors::claims::Claims, handlers::{orders::create_order, promotions::find_promotion, shipping::quote_shipping, users::ensure_stripe_customer}, mailer::{outbox::enqueue_order_email, templates::OrderEmail}};
 
 #[post("/")]
 async fn checkout(
     pool: web::Data<PgPool>,
     client: web::Data<Client>,
+    query: web::Query<PromotionQuery>,
+    credit_query: web::Query<CreditQuery>,
+    shipping_query: web::Query<ShippingQuery>,
     claims: Claims,
 ) -> Result<impl Responder> {
     remove_checkoutsessions(pool.clone(), &client, claims.sub.clone()).await?;
@@ -42,42 +49,41 @@ async fn checkout(
         None => return Err(error::ErrorBadRequest("Unable to find cart")),
     };
 
-    let update_cart_items = cart_items.clone();
+    // price the cart with the discount code and shipping before any stock is held for it
+    let code = query.into_inner().code;
+    let country = shipping_query.country();
+    let user_id = claims.sub.clone();
     let cloned_pool = pool.clone();
-    let enough_stock = web::block(move || {
+    let shipping_country = country.clone();
+    let (summary, shipping) = web::block(move || {
         let mut conn = cloned_pool.get().unwrap();
-        let enough_stock = update_cart_items.iter().try_for_each(|item| {
-            let id = item.product_id.clone();
-            let new_quantity = item.quantity.clone();
-            let product = db_get_product_by_id(&mut conn, id).unwrap();
-            if product.inventory.unwrap_or(0) < new_quantity {
-                return Err(());
-            }
-            Ok(())
-        });
 
-        if enough_stock.is_err() {
-            return Err(());
+        let mut summary = CartSummary::new(db_get_cart_with_products(&mut conn, user_id)?);
+        if let Some(code) = code {
+            summary.apply_promotion(find_promotion(&mut conn, code)?);
         }
 
-        update_cart_items.iter().for_each(|item| {
-            let id = item.product_id.clone();
-            let new_quantity = item.quantity.clone();
-            let product = db_get_product_by_id(&mut conn, id).unwrap();
-            let new_product = product::NewProduct{
-                id: Some(product.id.clone()),
-                inventory: Some(product.inventory.unwrap_or(0) - new_quantity),
-                ..Default::default()
-            };
-            db_update_product(&mut conn, new_product).unwrap();
-        });
-        enough_stock
+        let shipping = quote_shipping(&mut conn, shipping_country, &summary.subtotal, &summary.package_weight)?;
+
+        Ok::<_, diesel::result::Error>((summary, shipping))
     })
-    .await?;
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
 
-    if enough_stock.is_err() {
-        return Err(error::ErrorBadRequest("Not enough stock"));
+    if let Some(promotion_error) = summary.promotion_error {
+        return Ok(HttpResponse::BadRequest().json(promotion_error));
     }
+    let promotion = summary.promotion;
+
+    let shipping = match shipping {
+        Ok(shipping) => shipping,
+        Err(shipping_error) => return Ok(HttpResponse::BadRequest().json(shipping_error)),
+    };
+
+    let allowed_country = match serde_json::from_value::<CreateCheckoutSessionShippingAddressCollectionAllowedCountries>(serde_json::Value::String(country.clone())) {
+        Ok(allowed_country) => allowed_country,
+        Err(_) => return Ok(HttpResponse::BadRequest().json(ShippingError::NotShipped { country })),
+    };
 
     // check if user exists
     let user = match user {
@@ -85,40 +91,103 @@ async fn checkout(
         None => return Err(error::ErrorBadRequest("User does not exist")),
     };
 
+    // users without a stripe customer get one on their first checkout
+    let user = ensure_stripe_customer(&pool, &client, user).await?;
+
     // get the stripe customer object from stripe api
     let customer:Customer = client.get(&(format!("/customers/{}", user.stripe_id.unwrap())))
         .await
         .map_err(error::ErrorInternalServerError)?;
 
-    // create a checkout session
-    let checkout_session = {
-        let frontend_url = std::env::var("CLIENT_URL").expect("CLIENT_URL must be set");
-        let success_url = format!("{}/checkout-approved", frontend_url);
-        let cancel_url = format!("{}/checkout-canceled", frontend_url);
-
-        let mut params = stripe::CreateCheckoutSession::new();
-        params.success_url = Some(&success_url);
-        params.cancel_url = Some(&cancel_url);
-        params.customer = Some(customer.id);
-        params.mode = Some(CheckoutSessionMode::Payment);
-        params.shipping_address_collection = Some(stripe::CreateCheckoutSessionShippingAddressCollection{
-            allowed_countries: vec![CreateCheckoutSessionShippingAddressCollectionAllowedCountries::Us],
-            ..Default::default()});
-        params.expires_at = Some((chrono::Utc::now() + chrono::Duration::hours(1)).timestamp());
-        params.line_items = Some(cart_items.clone().into_iter().map(|item| {
-            let product = db_get_product_by_id(&mut pool.get().unwrap(), item.product_id).unwrap();
-            stripe::CreateCheckoutSessionLineItems {
-                price: Some(product.price_id.unwrap()),
-                quantity: Some(item.quantity as u64),
-                ..Default::default()
-            }
-        }).collect());
-        
-        params.expand = &["line_items", "line_items.data.price.product"];
+    // prefill the session's shipping details from the default address in the user's address book
+    let user_id = claims.sub.clone();
+    let cloned_pool = pool.clone();
+    let default_address = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
+        db_get_default_shipping_address(&mut conn, user_id)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    if let Some(default_address) = default_address {
+        let shipping = UpdateCustomerShipping {
+            address: UpdateCustomerShippingAddress {
+                line1: Some(default_address.line1),
+                line2: default_address.line2,
+                city: Some(default_address.city),
+                state: default_address.state,
+                postal_code: Some(default_address.postal_code),
+                country: Some(default_address.country),
+            },
+            name: default_address.name,
+            phone: default_address.phone,
+        };
+        Customer::update(&client, &customer.id, UpdateCustomer { shipping: Some(shipping), ..Default::default() })
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+    }
+
+    // stock, the use of the discount code and store credit are held right before the session is created, so
+    // nothing is left taken when stripe fails on the way there. they are given back when the session expires
+    let held_stock = cart_items.iter().map(|item| (item.product_id.clone(), item.quantity)).collect::<Vec<_>>();
+    let promotion_id = promotion.as_ref().map(|promotion| promotion.promotion_id.clone());
+    let credit_amount = credit_query.use_credit.unwrap_or(false).then_some(summary.total);
+    let user_id = claims.sub.clone();
+    let cloned_pool = pool.clone();
+    let (stock, promotion_id_held) = (held_stock.clone(), promotion_id.clone());
+    let credit = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
+        hold_checkout(&mut conn, user_id, stock, promotion_id_held, credit_amount)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let credit = match credit {
+        Ok(credit) => credit,
+        Err(CheckoutHoldError::NotEnoughStock) => return Err(error::ErrorBadRequest("Not enough stock")),
+        Err(CheckoutHoldError::UsageLimitReached) => {
+            let code = promotion.map(|promotion| promotion.code).unwrap_or_default();
+            return Ok(HttpResponse::BadRequest().json(PromotionError::UsageLimitReached { code }));
+        },
+    };
 
-        CheckoutSession::create(&client, params).await.map_err(error::ErrorInternalServerError)?
+    let line_items = cart_items.into_iter().map(|item| {
+        let product = db_get_product_by_id(&mut pool.get().unwrap(), item.product_id).unwrap();
+        stripe::CreateCheckoutSessionLineItems {
+            price: Some(product.price_id.unwrap()),
+            quantity: Some(item.quantity as u64),
+            ..Default::default()
+        }
+    }).collect();
+
+    let checkout_session = create_checkout_session(&client, customer.id, line_items, allowed_country, shipping, promotion.as_ref(), credit.as_ref()).await;
+
+    let cloned_pool = pool.clone();
+    let checkout_session = match checkout_session {
+        Ok(checkout_session) => checkout_session,
+        Err(e) => {
+            let credit_id = credit.map(|credit| credit.id);
+            web::block(move || {
+                let mut conn = cloned_pool.get().unwrap();
+                release_checkout_holds(&mut conn, held_stock, promotion_id, credit_id)
+            })
+            .await?
+            .map_err(error::ErrorInternalServerError)?;
+
+            return Err(e);
+        },
     };
 
+    if let Some(credit) = credit {
+        let session_id = checkout_session.id.to_string();
+        web::block(move || {
+            let mut conn = cloned_pool.get().unwrap();
+            db_attach_credit_to_session(&mut conn, credit.id, session_id)
+        })
+        .await?
+        .map_err(error::ErrorInternalServerError)?;
+    }
+
     log::info!(
         "created a {} checkout session for {} {:?} for {} {} at {}",
         checkout_session.payment_status,
@@ -136,6 +205,166 @@ async fn checkout(
     Ok(HttpResponse::Ok().json(checkout_session.url.unwrap()))
 }
 
+enum CheckoutHoldError {
+    NotEnoughStock,
+    UsageLimitReached,
+}
+
+// take the cart's stock, a use of the discount code and the store credit for a checkout, all of them or none
+fn hold_checkout(
+    conn: &mut PgConnection,
+    user: String,
+    held_stock: Vec<(String, i32)>,
+    promotion_id: Option<String>,
+    credit_amount: Option<BigDecimal>,
+) -> Result<Result<Option<CreditEntry>, CheckoutHoldError>, diesel::result::Error> {
+    conn.transaction(|conn| {
+        if !db_take_stock(conn, held_stock.clone())? {
+            return Ok(Err(CheckoutHoldError::NotEnoughStock));
+        }
+
+        if let Some(promotion_id) = promotion_id {
+            if db_redeem_promotion(conn, promotion_id)? == 0 {
+                db_restock_products(conn, held_stock)?;
+                return Ok(Err(CheckoutHoldError::UsageLimitReached));
+            }
+        }
+
+        let credit = match credit_amount {
+            Some(amount) => db_hold_credit(conn, user, amount)?,
+            None => None,
+        };
+
+        Ok(Ok(credit))
+    })
+}
+
+// give back what hold_checkout took when no session could be created for it
+fn release_checkout_holds(
+    conn: &mut PgConnection,
+    held_stock: Vec<(String, i32)>,
+    promotion_id: Option<String>,
+    credit_id: Option<String>,
+) -> Result<(), diesel::result::Error> {
+    conn.transaction(|conn| {
+        db_restock_products(conn, held_stock)?;
+        if let Some(promotion_id) = promotion_id {
+            db_release_promotion(conn, promotion_id)?;
+        }
+        if let Some(credit_id) = credit_id {
+            db_cancel_credit_hold(conn, credit_id)?;
+        }
+
+        Ok(())
+    })
+}
+
+async fn create_checkout_session(
+    client: &web::Data<Client>,
+    customer_id: CustomerId,
+    line_items: Vec<stripe::CreateCheckoutSessionLineItems>,
+    allowed_country: CreateCheckoutSessionShippingAddressCollectionAllowedCountries,
+    shipping: Vec<ShippingQuote>,
+    promotion: Option<&AppliedPromotion>,
+    credit: Option<&CreditEntry>,
+) -> Result<CheckoutSession> {
+    let mut metadata = HashMap::new();
+    let mut amount_off = BigDecimal::from(0);
+
+    if let Some(promotion) = promotion {
+        metadata.insert("promotion_id".to_string(), promotion.promotion_id.clone());
+        metadata.insert("promotion_code".to_string(), promotion.code.clone());
+        metadata.insert("discount".to_string(), promotion.discount.to_string());
+        amount_off += promotion.discount.clone();
+    }
+
+    if let Some(credit) = credit {
+        let store_credit = -credit.amount.clone();
+        metadata.insert("store_credit".to_string(), store_credit.to_string());
+        amount_off += store_credit;
+    }
+
+    // the discount and store credit are given as a single use coupon for their exact amount
+    let coupon = if amount_off > BigDecimal::from(0) {
+        let name = promotion.map(|promotion| promotion.code.clone()).unwrap_or("Store credit".to_string());
+
+        let mut params = CreateCoupon::new();
+        params.amount_off = (amount_off * BigDecimal::from(100)).to_i64();
+        params.currency = Some(Currency::USD);
+        params.duration = Some(CouponDuration::Once);
+        params.max_redemptions = Some(1);
+        params.name = Some(&name);
+
+        Some(Coupon::create(client, params).await.map_err(error::ErrorInternalServerError)?)
+    } else {
+        None
+    };
+
+    let frontend_url = std::env::var("CLIENT_URL").expect("CLIENT_URL must be set");
+    let success_url = format!("{}/checkout-approved", frontend_url);
+    let cancel_url = format!("{}/checkout-canceled", frontend_url);
+
+    let mut params = stripe::CreateCheckoutSession::new();
+    params.success_url = Some(&success_url);
+    params.cancel_url = Some(&cancel_url);
+    params.customer = Some(customer_id);
+    params.mode = Some(CheckoutSessionMode::Payment);
+    params.shipping_address_collection = Some(stripe::CreateCheckoutSessionShippingAddressCollection{
+        allowed_countries: vec![allowed_country],
+        ..Default::default()});
+    params.phone_number_collection = Some(stripe::CreateCheckoutSessionPhoneNumberCollection { enabled: true });
+    // stripe accepts at most five shipping options, a zone is never given more active methods than that
+    if !shipping.is_empty() {
+        params.shipping_options = Some(shipping.into_iter().map(|quote| stripe::CreateCheckoutSessionShippingOptions {
+            shipping_rate_data: Some(shipping_rate_data(quote)),
+            ..Default::default()
+        }).collect());
+    }
+    params.expires_at = Some((chrono::Utc::now() + chrono::Duration::hours(1)).timestamp());
+    params.line_items = Some(line_items);
+
+    if let Some(coupon) = coupon {
+        params.discounts = Some(vec![stripe::CreateCheckoutSessionDiscounts {
+            coupon: Some(coupon.id.to_string()),
+            ..Default::default()
+        }]);
+    }
+    // the expired webhook gives back the promotion use reserved for the session
+    if !metadata.is_empty() {
+        params.metadata = Some(metadata);
+    }
+
+    params.expand = &["line_items", "line_items.data.price.product"];
+
+    CheckoutSession::create(client, params).await.map_err(error::ErrorInternalServerError)
+}
+
+fn shipping_rate_data(quote: ShippingQuote) -> stripe::CreateCheckoutSessionShippingOptionsShippingRateData {
+    let delivery_estimate = stripe::CreateCheckoutSessionShippingOptionsShippingRateDataDeliveryEstimate {
+        minimum: quote.min_days.map(|days| stripe::CreateCheckoutSessionShippingOptionsShippingRateDataDeliveryEstimateMinimum {
+            unit: stripe::CreateCheckoutSessionShippingOptionsShippingRateDataDeliveryEstimateMinimumUnit::BusinessDay,
+            value: days as i64,
+        }),
+        maximum: quote.max_days.map(|days| stripe::CreateCheckoutSessionShippingOptionsShippingRateDataDeliveryEstimateMaximum {
+            unit: stripe::CreateCheckoutSessionShippingOptionsShippingRateDataDeliveryEstimateMaximumUnit::BusinessDay,
+            value: days as i64,
+        }),
+    };
+
+    stripe::CreateCheckoutSessionShippingOptionsShippingRateData {
+        delivery_estimate: Some(delivery_estimate),
+        display_name: quote.name,
+        fixed_amount: Some(stripe::CreateCheckoutSessionShippingOptionsShippingRateDataFixedAmount {
+            amount: (quote.cost * BigDecimal::from(100)).to_i64().unwrap_or(0),
+            currency: Currency::USD,
+            ..Default::default()
+        }),
+        metadata: Some(HashMap::from([("shipping_method_id".to_string(), quote.method_id)])),
+        type_: Some(stripe::CreateCheckoutSessionShippingOptionsShippingRateDataType::FixedAmount),
+        ..Default::default()
+    }
+}
+
 #[post("/cancel")]
 async fn cancel_checkout(
     pool: web::Data<PgPool>,
@@ -179,27 +408,88 @@ pub(crate) async fn checkout_success(
 ) -> Result<(), Box<dyn std::error::Error>> {
     let stripe_user_id = checkout_session.customer.clone().unwrap().id().to_string();
 
-    let address = {
-        checkout_session.shipping_details.clone().unwrap().address.clone().unwrap().line1.clone().unwrap_or("".to_string()) + ", " +
-        &checkout_session.shipping_details.clone().unwrap().address.clone().unwrap().line2.clone().unwrap_or("".to_string()) + ", " +
-        &checkout_session.shipping_details.clone().unwrap().address.clone().unwrap().city.clone().unwrap_or("".to_string()) + " " +
-        &checkout_session.shipping_details.clone().unwrap().address.clone().unwrap().state.clone().unwrap_or("".to_string()) + " " +
-        &checkout_session.shipping_details.clone().unwrap().address.clone().unwrap().postal_code.clone().unwrap_or("".to_string()) + " " +
-        &checkout_session.shipping_details.clone().unwrap().address.clone().unwrap().country.clone().unwrap_or("".to_string())
+    let shipping_details = checkout_session.shipping_details.clone().unwrap();
+    let stripe_address = shipping_details.address.clone().unwrap_or_default();
+    let address = ShippingAddress {
+        name: shipping_details.name.clone().unwrap_or_default(),
+        // the phone number is collected with the customer details unless stripe put one on the shipping details
+        phone: shipping_details.phone.clone()
+            .or(checkout_session.customer_details.as_ref().and_then(|customer_details| customer_details.phone.clone())),
+        line1: stripe_address.line1,
+        line2: stripe_address.line2,
+        city: stripe_address.city,
+        state: stripe_address.state,
+        postal_code: stripe_address.postal_code,
+        country: stripe_address.country,
+    }
+    .normalize();
+
+    // the shipping option the customer picked
+    let shipping_rate = match checkout_session.shipping_cost.as_ref().and_then(|shipping_cost| shipping_cost.shipping_rate.clone()) {
+        Some(Expandable::Object(shipping_rate)) => Some(*shipping_rate),
+        Some(Expandable::Id(shipping_rate_id)) => Some(ShippingRate::retrieve(&client, &shipping_rate_id, &[]).await?),
+        None => None,
     };
+    let shipping_cost = checkout_session.shipping_cost.as_ref()
+        .map(|shipping_cost| BigDecimal::from(shipping_cost.amount_total) / BigDecimal::from(100));
 
-    create_order(
+    let order = create_order(
         pool.clone(), 
         client, 
         checkout_session.customer.clone().unwrap().id().to_string(),
-        checkout_session.shipping_details.clone().unwrap().name.clone().unwrap_or("".to_string()),
         address,
     ).await?;
 
+    let metadata = checkout_session.metadata.clone().unwrap_or_default();
+    let session_id = checkout_session.id.to_string();
+    let payment_intent_id = checkout_session.payment_intent.as_ref().map(|payment_intent| payment_intent.id().to_string());
+    let amount_paid = checkout_session.amount_total.map(|amount_total| BigDecimal::from(amount_total) / BigDecimal::from(100));
+
     // convert stripe id to auth0 id and then delete cart associated with auth0 id
     let cart = web::block(move || {
         let mut conn = pool.get().unwrap();
 
+        // refunds are issued against the payment of the order, for at most what it charged
+        if payment_intent_id.is_some() {
+            db_update_order(&mut conn, order.id.clone(), NewOrder {
+                payment_intent_id,
+                amount_paid: amount_paid.map(|amount_paid| amount_paid.with_scale(2)),
+                ..Default::default()
+            })?;
+        }
+
+        // record the discount code used on the order, its use was already counted when the session was created
+        if let Some(promotion_code) = metadata.get("promotion_code") {
+            db_update_order(&mut conn, order.id.clone(), NewOrder {
+                promotion_code: Some(promotion_code.clone()),
+                discount: metadata.get("discount").and_then(|discount| BigDecimal::from_str(discount).ok()),
+                ..Default::default()
+            })?;
+        }
+
+        if let (Some(shipping_rate), Some(shipping_cost)) = (shipping_rate, shipping_cost) {
+            db_update_order(&mut conn, order.id.clone(), NewOrder {
+                shipping_method_id: shipping_rate.metadata.get("shipping_method_id").cloned(),
+                shipping_method: shipping_rate.display_name,
+                shipping_cost: Some(shipping_cost.with_scale(2)),
+                ..Default::default()
+            })?;
+        }
+
+        // the credit held for the session now belongs to the order
+        if let Some(store_credit) = metadata.get("store_credit") {
+            db_update_order(&mut conn, order.id.clone(), NewOrder {
+                store_credit: BigDecimal::from_str(store_credit).ok(),
+                ..Default::default()
+            })?;
+            db_assign_checkout_credit(&mut conn, session_id, order.id.clone())?;
+        }
+
+        // a failed email should not fail the webhook, stripe would retry and create a second order
+        if let Err(e) = enqueue_order_email(&mut conn, OrderEmail::Confirmation, order.id.clone()) {
+            log::error!("failed to queue confirmation email for {}: {}", order.id, e);
+        }
+
         let user = db_user_stripe_to_user_id(&mut conn, stripe_user_id.clone())?;
 
         // delete the cart
@@ -217,25 +507,29 @@ pub(crate) async fn checkout_expired(
     checkout_session: CheckoutSession,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let stripe_user_id = checkout_session.customer.clone().unwrap().id().to_string();
+    let session_id = checkout_session.id.to_string();
+    let promotion_id = checkout_session.metadata.clone().unwrap_or_default().get("promotion_id").cloned();
 
     // convert stripe id to auth0 id and then delete cart associated with auth0 id
     web::block(move || {
         let mut conn = pool.get().unwrap();
 
+        // give back any store credit the session was holding
+        if let Err(e) = db_release_checkout_credit(&mut conn, session_id.clone()) {
+            log::error!("failed to release store credit held by {}: {}", session_id, e);
+        }
+
         let user = db_user_stripe_to_user_id(&mut conn, stripe_user_id.clone()).unwrap();
         let cart = db_get_cart_items_by_user_id(&mut conn, user.clone().unwrap().id).unwrap().unwrap();
-        // // for each item deleted from the cart, update the product inventory
-        cart.iter().for_each(|item| {
-            let id = item.product_id.clone();
-            let new_quantity = item.quantity.clone();
-            let product = db_get_product_by_id(&mut conn, id).unwrap();
-            let new_product = product::NewProduct{
-                id: Some(product.id.clone()),
-                inventory: Some(new_quantity + product.inventory.unwrap_or(0)),
-                ..Default::default()
-            };
-            db_update_product(&mut conn, new_product).unwrap();
-        });
+        // for each item held by the session, put it back in the product inventory
+        db_restock_products(&mut conn, cart.iter().map(|item| (item.product_id.clone(), item.quantity)).collect()).unwrap();
+
+        // the discount code can be used by someone else
+        if let Some(promotion_id) = promotion_id {
+            if let Err(e) = db_release_promotion(&mut conn, promotion_id.clone()) {
+                log::error!("failed to release promotion {} reserved by {}: {}", promotion_id, session_id, e);
+            }
+        }
     })
     .await?;
 
@@ -0,0 +1,155 @@
+use actix_web::{error, get, post, web, HttpResponse, Responder, Result};
+use bigdecimal::BigDecimal;
+
+use crate::database::credit::{
+    db_add_credit, db_get_credit_entries, db_get_gift_cards, db_issue_gift_card, db_redeem_gift_card,
+    db_refund_order_to_credit,
+};
+use crate::database::users::db_get_user;
+use crate::extractors::claims::Claims;
+use crate::extractors::permissions::{OrdersRead, OrdersRefund, RequirePermission, UsersManage};
+use crate::middleware::permission_guard::PermissionGuard;
+use crate::models::credit::{CreditBalance, CreditGrant, GiftCardIssue, GiftCardRedeem, NewCreditEntry, NewGiftCard};
+use crate::models::dbpool::PgPool;
+
+// the store credit balance of the logged in user and how it was earned and spent
+#[get("/credit")]
+pub(crate) async fn get_credit(
+    pool: web::Data<PgPool>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let entries = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_credit_entries(&mut conn, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(CreditBalance::new(entries)))
+}
+
+#[post("/credit/redeem")]
+pub(crate) async fn redeem_gift_card(
+    pool: web::Data<PgPool>,
+    redeem: web::Json<GiftCardRedeem>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let entry = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_redeem_gift_card(&mut conn, redeem.into_inner().code, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match entry {
+        Ok(entry) => Ok(HttpResponse::Ok().json(entry)),
+        Err(gift_card_error) => Ok(HttpResponse::BadRequest().json(gift_card_error)),
+    }
+}
+
+#[get("/credit/gift_card", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn get_gift_cards(
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder> {
+    let gift_cards = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_gift_cards(&mut conn)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(gift_cards))
+}
+
+#[post("/credit/gift_card", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn issue_gift_card(
+    pool: web::Data<PgPool>,
+    issue: web::Json<GiftCardIssue>,
+    staff: RequirePermission<UsersManage>,
+) -> Result<impl Responder> {
+    let issue = issue.into_inner();
+    if issue.amount <= BigDecimal::from(0) {
+        return Ok(HttpResponse::BadRequest().body("amount must be positive"));
+    }
+
+    let gift_card = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_issue_gift_card(&mut conn, NewGiftCard {
+            code: issue.code.map(|code| code.trim().to_uppercase()),
+            initial_balance: issue.amount.with_scale(2),
+            balance: issue.amount.with_scale(2),
+            issued_by: staff.sub.clone(),
+            note: issue.note,
+            expires_at: issue.expires_at,
+        })
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(gift_card))
+}
+
+// add credit straight to a user's balance
+#[post("/credit/grant/{user_id}", wrap = "PermissionGuard::<UsersManage>::new()")]
+pub(crate) async fn grant_credit(
+    pool: web::Data<PgPool>,
+    user_id: web::Path<String>,
+    grant: web::Json<CreditGrant>,
+) -> Result<impl Responder> {
+    let grant = grant.into_inner();
+    if grant.amount <= BigDecimal::from(0) {
+        return Ok(HttpResponse::BadRequest().body("amount must be positive"));
+    }
+
+    let entry = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        let user = match db_get_user(&mut conn, user_id.into_inner())? {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+
+        db_add_credit(&mut conn, NewCreditEntry {
+            user_id: user.id,
+            amount: grant.amount.with_scale(2),
+            reason: "grant".to_string(),
+            note: grant.note,
+            ..Default::default()
+        })
+        .map(Some)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match entry {
+        Some(entry) => Ok(HttpResponse::Ok().json(entry)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+// refund an order, or part of it, as store credit instead of back to the card
+#[post("/refund/{id}/credit", wrap = "PermissionGuard::<OrdersRead>::new()")]
+pub(crate) async fn refund_order_to_credit(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    grant: web::Json<CreditGrant>,
+    _permission: RequirePermission<OrdersRefund>,
+) -> Result<impl Responder> {
+    let grant = grant.into_inner();
+    if grant.amount <= BigDecimal::from(0) {
+        return Ok(HttpResponse::BadRequest().body("amount must be positive"));
+    }
+
+    let entry = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_refund_order_to_credit(&mut conn, id.into_inner(), grant.amount.with_scale(2), grant.note)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match entry {
+        Some(Ok(entry)) => Ok(HttpResponse::Ok().json(entry)),
+        Some(Err(refund_error)) => Ok(HttpResponse::BadRequest().json(refund_error)),
+        None => Ok(HttpResponse::NotFound().body("Order not found")),
+    }
+}
\ No newline at end of file
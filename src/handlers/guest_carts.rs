@@ -0,0 +1,168 @@
+use actix_web::{get, web, HttpResponse, Responder, Result, error, post, put};
+
+use crate::database::carts::db_validate_cart_lines;
+use crate::database::guest_carts::{
+    db_create_guest_cart, db_delete_expired_guest_carts, db_get_guest_cart, db_get_guest_cart_items,
+    db_merge_guest_cart, db_replace_guest_cart_items, db_upsert_guest_cart_item,
+};
+use crate::extractors::cart_token::CartToken;
+use crate::extractors::claims::Claims;
+use crate::models::cart::{CartItemSubmit, CartSubmit, CartValidationErrors};
+use crate::models::dbpool::PgPool;
+use crate::models::guest_cart::{guest_cart_ttl, GuestCartCreated, GuestCartItem};
+
+// start a cart for a shopper that is not logged in
+#[post("/guest")]
+pub(crate) async fn create_guest_cart(
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder> {
+    let ttl = guest_cart_ttl();
+
+    let guest_cart = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        // clean up the carts nobody came back for
+        db_delete_expired_guest_carts(&mut conn)?;
+
+        db_create_guest_cart(&mut conn, chrono::Utc::now().naive_utc() + ttl)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let token = CartToken::issue(guest_cart.id, guest_cart.expires_at)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .cookie(CartToken::cookie(token.clone(), ttl))
+        .json(GuestCartCreated {
+            token,
+            expires_at: guest_cart.expires_at,
+        }))
+}
+
+#[get("/guest")]
+pub(crate) async fn get_guest_cart_items(
+    pool: web::Data<PgPool>,
+    cart_token: CartToken,
+) -> Result<impl Responder> {
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        if db_get_guest_cart(&mut conn, cart_token.sub.clone())?.is_none() {
+            return Ok(None);
+        }
+
+        db_get_guest_cart_items(&mut conn, cart_token.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match cart_items {
+        Some(cart_items) => Ok(HttpResponse::Ok().json(cart_items)),
+        None => Ok(HttpResponse::NotFound().body("Guest cart not found")),
+    }
+}
+
+#[post("/guest/add")]
+pub(crate) async fn add_to_guest_cart(
+    pool: web::Data<PgPool>,
+    new_cart: web::Json<CartItemSubmit>,
+    cart_token: CartToken,
+) -> Result<impl Responder> {
+    let cart_item = GuestCartItem {
+        guest_cart_id: cart_token.sub,
+        product_id: new_cart.product_id.clone(),
+        quantity: new_cart.quantity,
+    };
+
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        if db_get_guest_cart(&mut conn, cart_item.guest_cart_id.clone())?.is_none() {
+            return Ok(None);
+        }
+
+        let errors = db_validate_cart_lines(&mut conn, vec![(cart_item.product_id.clone(), cart_item.quantity)])?;
+        if !errors.is_empty() {
+            return Ok(Some(Err(CartValidationErrors { errors })));
+        }
+
+        db_upsert_guest_cart_item(&mut conn, cart_item).map(|cart_item| Some(Ok(cart_item)))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match cart_items {
+        Some(Ok(cart_items)) => Ok(HttpResponse::Ok().json(cart_items)),
+        Some(Err(errors)) => Ok(HttpResponse::BadRequest().json(errors)),
+        None => Ok(HttpResponse::NotFound().body("Guest cart not found")),
+    }
+}
+
+#[put("/guest/update_cart")]
+pub(crate) async fn update_guest_cart(
+    pool: web::Data<PgPool>,
+    cart_submit: web::Json<CartSubmit>,
+    cart_token: CartToken,
+) -> Result<impl Responder> {
+    let cart = cart_submit.into_inner().cart;
+
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        if db_get_guest_cart(&mut conn, cart_token.sub.clone())?.is_none() {
+            return Ok(None);
+        }
+
+        let errors = db_validate_cart_lines(&mut conn, cart.clone().into_iter().collect())?;
+        if !errors.is_empty() {
+            return Ok(Some(Err(CartValidationErrors { errors })));
+        }
+
+        let new_cart_items = cart.into_iter()
+            .map(|(product_id, quantity)| GuestCartItem {
+                guest_cart_id: cart_token.sub.clone(),
+                product_id,
+                quantity,
+            })
+            .collect::<Vec<GuestCartItem>>();
+
+        db_replace_guest_cart_items(&mut conn, cart_token.sub, new_cart_items).map(|cart_items| Some(Ok(cart_items)))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match cart_items {
+        Some(Ok(cart_items)) => Ok(HttpResponse::Ok().json(cart_items)),
+        Some(Err(errors)) => Ok(HttpResponse::BadRequest().json(errors)),
+        None => Ok(HttpResponse::NotFound().body("Guest cart not found")),
+    }
+}
+
+// called once the guest logs in, moves the guest cart into their cart and forgets the cart token
+#[post("/guest/merge")]
+pub(crate) async fn merge_guest_cart(
+    pool: web::Data<PgPool>,
+    cart_token: CartToken,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let cart_items = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        if db_get_guest_cart(&mut conn, cart_token.sub.clone())?.is_none() {
+            return Ok(None);
+        }
+
+        db_merge_guest_cart(&mut conn, cart_token.sub, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let mut removal_cookie = CartToken::cookie(String::new(), chrono::Duration::zero());
+    removal_cookie.make_removal();
+
+    match cart_items {
+        Some(cart_items) => Ok(HttpResponse::Ok().cookie(removal_cookie).json(cart_items)),
+        None => Ok(HttpResponse::NotFound().cookie(removal_cookie).body("Guest cart not found")),
+    }
+}
@@ -1,5 +1,13 @@
 pub mod products;
+pub mod abandoned_carts;
 pub mod carts;
+pub mod guest_carts;
 pub mod users;
 pub mod checkout;
-pub mod orders;
\ No newline at end of file
+pub mod credit;
+pub mod promotions;
+pub mod shipping;
+pub mod orders;
+pub mod addresses;
+pub mod returns;
+pub mod api_keys;
\ No newline at end of file
@@ -1,9 +1,11 @@
-use actix_web::{get, web, Responder, Result, HttpResponse, error, post};
+use actix_web::{get, web, Responder, Result, HttpResponse, error, post, put};
+use bigdecimal::BigDecimal;
+use diesel::OptionalExtension;
 use stripe::{Client, Product};
 
-use crate::{models::{dbpool::PgPool, order::{NewOrder}}, database::{orders::{db_create_order, db_delete_order, db_get_all_orders, db_get_expanded_order_by_id, db_get_expanded_orders, db_get_expanded_orders_by_user_id, db_update_order}, carts::db_get_cart_items_by_user_id, users::db_user_stripe_to_user_id}};
+use crate::{extractors::{claims::Claims, permissions::{OrdersFulfill, OrdersRead, OrdersWrite, RequirePermission}}, middleware::permission_guard::PermissionGuard, models::{address::{AddressValidationErrors, ShippingAddress}, dbpool::PgPool, order::{NewOrder, Order}, pagination::PageQuery, shipment::ShipmentPayload}, database::{orders::{db_abort_cancel_order, db_cancel_order, db_create_order, db_delete_order, db_get_all_orders, db_get_expanded_order_by_id, db_get_expanded_orders, db_get_expanded_order_by_user_id, db_get_expanded_orders_by_user_id, db_get_expanded_orders_page_by_user_id, db_get_order_by_id, db_start_cancel_order, db_update_order, db_update_order_address}, carts::db_get_cart_items_by_user_id, products::db_get_multiple_products_by_id, shipments::{db_create_shipment, db_get_shipments_by_order_id}, users::db_user_stripe_to_user_id}, mailer::{outbox::enqueue_order_email, templates::OrderEmail}, handlers::returns::refund_payment};
 
-#[get("")]
+#[get("", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn get_orders(
     pool: web::Data<PgPool>,
 ) -> Result<impl Responder>{
@@ -21,7 +23,7 @@ async fn get_orders(
     }
 }
 
-#[get("/id/{id}")]
+#[get("/id/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn get_order_by_id(
     pool: web::Data<PgPool>,
     id: web::Path<String>,
@@ -29,15 +31,18 @@ async fn get_order_by_id(
 
     let order = web::block(move || {
         let mut conn = pool.get().unwrap();
-        db_get_expanded_order_by_id(&mut conn, id.to_string())
+        db_get_expanded_order_by_id(&mut conn, id.to_string()).optional()
     })
     .await?
     .map_err(error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().json(order))
+    match order {
+        Some(order) => Ok(HttpResponse::Ok().json(order)),
+        None => Ok(HttpResponse::NotFound().body("Order not found")),
+    }
 }
 
-#[get("/expand")]
+#[get("/expand", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn get_expanded_orders(
     pool: web::Data<PgPool>,
 ) -> Result<impl Responder>{
@@ -54,7 +59,7 @@ async fn get_expanded_orders(
     }
 }
 
-#[get("/expand/user/{id}")]
+#[get("/expand/user/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn get_expanded_orders_by_user_id(
     pool: web::Data<PgPool>,
     id: web::Path<String>,
@@ -72,10 +77,47 @@ async fn get_expanded_orders_by_user_id(
     }
 }
 
-#[post("/create")]
+// the logged in user's own orders, newest first
+#[get("/mine")]
+async fn get_my_orders(
+    pool: web::Data<PgPool>,
+    query: web::Query<PageQuery>,
+    claims: Claims,
+) -> Result<impl Responder>{
+    let orders = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_expanded_orders_page_by_user_id(&mut conn, claims.sub, &query)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(orders))
+}
+
+#[get("/mine/{id}")]
+async fn get_my_order(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    claims: Claims,
+) -> Result<impl Responder>{
+    let order = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_expanded_order_by_user_id(&mut conn, claims.sub, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order {
+        Some(order) => Ok(HttpResponse::Ok().json(order)),
+        None => Ok(HttpResponse::NotFound().body("Order not found")),
+    }
+}
+
+#[post("/create", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn create_order_handler(
     pool: web::Data<PgPool>,
     order: web::Json<NewOrder>,
+    _permission: RequirePermission<OrdersWrite>,
 ) -> Result<impl Responder>{
 
     let order = web::block(move || {
@@ -88,11 +130,12 @@ async fn create_order_handler(
     Ok(HttpResponse::Ok().json(order))
 }
 
-#[post("/update/{id}")]
+#[post("/update/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn update_order(
     pool: web::Data<PgPool>,
     id: web::Path<String>,
     order: web::Json<NewOrder>,
+    _permission: RequirePermission<OrdersWrite>,
 ) -> Result<impl Responder>{
 
     let order = web::block(move || {
@@ -110,20 +153,177 @@ struct OrderStatus {
     status: String,
 }
 
-#[post("/update/{id}/status")]
+#[post("/update/{id}/status", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn update_order_status(
     pool: web::Data<PgPool>,
     id: web::Path<String>,
     status: web::Json<OrderStatus>,
+    _permission: RequirePermission<OrdersFulfill>,
 ) -> Result<impl Responder>{
 
     let order = web::block(move || {
         let mut conn = pool.get().unwrap();
-        db_update_order(&mut conn, id.to_string(), NewOrder{
+        let previous_status = db_get_order_by_id(&mut conn, id.to_string())?.status;
+
+        let order = db_update_order(&mut conn, id.to_string(), NewOrder{
             status: Some(status.status.clone()),
             updated_at: Some(chrono::Local::now().naive_local()),
             ..Default::default()
-        })
+        })?;
+
+        // let the customer know their order moved along
+        if previous_status != order.status {
+            if let Some(email) = OrderEmail::from_status(&order.status) {
+                if let Err(e) = enqueue_order_email(&mut conn, email, order.id.clone()) {
+                    log::error!("failed to queue {} email for {}: {}", email.kind(), order.id, e);
+                }
+            }
+        }
+
+        Ok::<Order, diesel::result::Error>(order)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(order))
+}
+
+// fix the recipient or shipping address of an order
+#[put("/update/{id}/address", wrap = "PermissionGuard::<OrdersRead>::new()")]
+async fn update_order_address(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    address: web::Json<ShippingAddress>,
+    _permission: RequirePermission<OrdersWrite>,
+) -> Result<impl Responder>{
+    let address = address.into_inner().normalize();
+    let errors = address.validate();
+    if !errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(AddressValidationErrors { errors }));
+    }
+
+    let order = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        if db_get_order_by_id(&mut conn, id.to_string()).optional()?.is_none() {
+            return Ok(None);
+        }
+
+        db_update_order_address(&mut conn, id.to_string(), address).map(Some)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order {
+        Some(order) => Ok(HttpResponse::Ok().json(order)),
+        None => Ok(HttpResponse::NotFound().body("Order not found")),
+    }
+}
+
+// record a package going out for an order, the customer is emailed its tracking details
+#[post("/ship/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
+async fn create_shipment(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    shipment: web::Json<ShipmentPayload>,
+    staff: RequirePermission<OrdersFulfill>,
+) -> Result<impl Responder>{
+    let shipment = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        let shipment = db_create_shipment(&mut conn, id.to_string(), shipment.into_inner(), staff.sub.clone())?;
+
+        if let Some(Ok(shipment)) = &shipment {
+            if let Err(e) = enqueue_order_email(&mut conn, OrderEmail::Shipped, shipment.order_id.clone()) {
+                log::error!("failed to queue {} email for {}: {}", OrderEmail::Shipped.kind(), shipment.order_id, e);
+            }
+        }
+
+        Ok::<_, diesel::result::Error>(shipment)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match shipment {
+        Some(Ok(shipment)) => Ok(HttpResponse::Created().json(shipment)),
+        Some(Err(shipment_error)) => Ok(HttpResponse::BadRequest().json(shipment_error)),
+        None => Ok(HttpResponse::NotFound().body("Order not found")),
+    }
+}
+
+#[get("/shipments/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
+async fn get_order_shipments(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder>{
+    let shipments = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_shipments_by_order_id(&mut conn, id.to_string())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(shipments))
+}
+
+// the customer calls off their order before it ships. what is left of the payment is refunded before the order is
+// canceled, so a failed refund leaves it to be fulfilled or canceled again
+#[post("/{id}/cancel")]
+async fn cancel_order(
+    pool: web::Data<PgPool>,
+    client: web::Data<Client>,
+    id: web::Path<String>,
+    claims: Claims,
+) -> Result<impl Responder>{
+    let cloned_pool = pool.clone();
+    let user = claims.sub.clone();
+    let order = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
+        db_start_cancel_order(&mut conn, user, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let (order, card_refund) = match order {
+        Some(Ok(started)) => started,
+        Some(Err(cancel_error)) => return Ok(HttpResponse::BadRequest().json(cancel_error)),
+        None => return Ok(HttpResponse::NotFound().body("Order not found")),
+    };
+
+    // everything paid with the card may already be back as store credit
+    let refund = if card_refund > BigDecimal::from(0) {
+        let payment_intent_id = order.payment_intent_id.clone().unwrap_or_default();
+        let metadata = std::collections::HashMap::from([("order_id".to_string(), order.id.clone())]);
+        refund_payment(&client, &payment_intent_id, Some(&card_refund), format!("cancel-{}", order.id), metadata).await
+            .map(|refund| BigDecimal::from(refund.amount) / BigDecimal::from(100))
+    } else {
+        Ok(BigDecimal::from(0))
+    };
+
+    let refunded = match refund {
+        Ok(refunded) => refunded,
+        Err(e) => {
+            log::error!("the refund for canceling order {} failed: {}", order.id, e);
+            let cloned_pool = pool.clone();
+            web::block(move || {
+                let mut conn = cloned_pool.get().unwrap();
+                db_abort_cancel_order(&mut conn, order.id)
+            })
+            .await?
+            .map_err(error::ErrorInternalServerError)?;
+
+            return Err(e);
+        },
+    };
+
+    let order = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        let order = db_cancel_order(&mut conn, claims.sub, order.id, refunded)?;
+
+        if let Err(e) = enqueue_order_email(&mut conn, OrderEmail::Canceled, order.id.clone()) {
+            log::error!("failed to queue {} email for {}: {}", OrderEmail::Canceled.kind(), order.id, e);
+        }
+
+        Ok::<Order, diesel::result::Error>(order)
     })
     .await?
     .map_err(error::ErrorInternalServerError)?;
@@ -131,10 +331,11 @@ async fn update_order_status(
     Ok(HttpResponse::Ok().json(order))
 }
 
-#[post("/delete/{id}")]
+#[post("/delete/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
 async fn delete_order(
     pool: web::Data<PgPool>,
     id: web::Path<String>,
+    _permission: RequirePermission<OrdersWrite>,
 ) -> Result<impl Responder>{
 
     let order = web::block(move || {
@@ -151,9 +352,8 @@ pub(crate) async fn create_order(
     pool: web::Data<PgPool>,
     client: web::Data<Client>,
     user: String,
-    name: String,
-    address: String,
-) -> Result<(), Box<dyn std::error::Error>> {
+    address: ShippingAddress,
+) -> Result<Order, Box<dyn std::error::Error>> {
 
     let order = web::block(move || {
         let mut conn = pool.get().unwrap();
@@ -166,12 +366,17 @@ pub(crate) async fn create_order(
             (product_id, serde_json::Value::Number(serde_json::Number::from(quantity)))
         }).collect::<serde_json::Map<String, serde_json::Value>>();
 
+        // the prices the customer is charged, later price changes don't change what a return refunds
+        let prices = db_get_multiple_products_by_id(&mut conn, cart_items.keys().cloned().collect())?
+            .into_iter()
+            .filter_map(|product| Some((product.id, serde_json::Value::String(product.price?.to_string()))))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+
         let order = NewOrder{
             user_id: Some(user.id),
             products: Some(serde_json::Value::Object(cart_items)),
-            name: Some(name),
-            address: Some(address),
-            ..Default::default()
+            prices: Some(serde_json::Value::Object(prices)),
+            ..NewOrder::from_address(address)
         };
 
         log::info!("new_order: {:?}", order);
@@ -216,5 +421,5 @@ pub(crate) async fn create_order(
         .map_err(error::ErrorInternalServerError)?;
     }
 
-    Ok(())
+    Ok(order)
 }
\ No newline at end of file
@@ -1,5 +1,3 @@
-use std::collections::HashSet;
-
 use actix_web::{delete, error, get, post, put, web, HttpResponse, Responder, Result};
 use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
 use stripe::Object;
@@ -10,7 +8,8 @@ use crate::database::products::{
     db_get_multiple_products_by_id, db_get_product_by_id, db_get_product_by_name,
     db_get_products_by_category, db_update_product,
 };
-use crate::extractors::claims::Claims;
+use crate::extractors::permissions::ProductsWrite;
+use crate::middleware::permission_guard::PermissionGuard;
 use crate::models::dbpool::PgPool;
 use crate::models::product::{self, NewProductPayload, ProductIds, UpdatePayload};
 
@@ -150,15 +149,14 @@ async fn get_active_products_by_category(
     Ok(HttpResponse::Ok().json(products))
 }
 
-#[post("/create")]
+#[post("/create", wrap = "PermissionGuard::<ProductsWrite>::new()")]
 async fn create_product(
     client: web::Data<stripe::Client>,
     new_product_payload: web::Json<NewProductPayload>,
-    // claims: Claims,
 ) -> Result<impl Responder> {
-    // if !claims.validate_roles(&HashSet::from(["admin".to_string()])) {
-    //     return Ok(HttpResponse::Unauthorized().finish());
-    // };
+    if let Err(e) = new_product_payload.physical.validate() {
+        return Ok(HttpResponse::BadRequest().body(e));
+    }
 
     let product_name = new_product_payload.name.clone();
     let mut new_product = stripe::CreateProduct::new(&product_name);
@@ -184,6 +182,9 @@ async fn create_product(
             String::from(new_product_payload.category.clone()),
         ),
     ]));
+    if let Some(metadata) = new_product.metadata.as_mut() {
+        metadata.extend(new_product_payload.physical.metadata());
+    }
 
     log::info!("new_product: {:?}", new_product);
 
@@ -203,17 +204,16 @@ async fn create_product(
     return Ok(HttpResponse::Ok().finish());
 }
 
-#[put("/update/{id}")]
+#[put("/update/{id}", wrap = "PermissionGuard::<ProductsWrite>::new()")]
 async fn update_product(
     pool: web::Data<PgPool>,
     client: web::Data<stripe::Client>,
     product_id: web::Path<String>,
     update_payload: web::Json<UpdatePayload>,
-    claims: Claims,
 ) -> Result<impl Responder> {
-    if !claims.validate_roles(&HashSet::from(["admin".to_string()])) {
-        return Ok(HttpResponse::Unauthorized().finish());
-    };
+    if let Err(e) = update_payload.physical.validate() {
+        return Ok(HttpResponse::BadRequest().body(e));
+    }
 
     let products = stripe::Product::list(
         &client,
@@ -251,14 +251,16 @@ async fn update_product(
             description: Some(update_payload.description.clone()),
             active: Some(is_active),
             images: Some(update_payload.images.clone()),
-            metadata: Some(std::collections::HashMap::from([(
-                String::from("inventory"),
-                String::from(
-                    (stripe_inventory + db_product.inventory.unwrap_or(0))
-                        .to_string()
-                        .as_str(),
-                ),
-            )])),
+            metadata: Some(std::collections::HashMap::from_iter(
+                update_payload.physical.metadata().into_iter().chain([(
+                    String::from("inventory"),
+                    String::from(
+                        (stripe_inventory + db_product.inventory.unwrap_or(0))
+                            .to_string()
+                            .as_str(),
+                    ),
+                )]),
+            )),
             ..Default::default()
         },
     )
@@ -268,16 +270,11 @@ async fn update_product(
     Ok(HttpResponse::Ok().json(product))
 }
 
-#[delete("/delete/{id}")]
+#[delete("/delete/{id}", wrap = "PermissionGuard::<ProductsWrite>::new()")]
 async fn delete_product(
     client: web::Data<stripe::Client>,
     product_id: web::Path<String>,
-    claims: Claims,
 ) -> Result<impl Responder> {
-    if !claims.validate_roles(&HashSet::from(["admin".to_string()])) {
-        return Ok(HttpResponse::Unauthorized().finish());
-    };
-
     let products = stripe::Product::list(
         &client,
         &stripe::ListProducts {
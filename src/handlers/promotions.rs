@@ -0,0 +1,88 @@
+use actix_web::{delete, error, get, post, put, web, HttpResponse, Responder, Result};
+use diesel::PgConnection;
+
+use crate::database::promotions::{
+    db_create_promotion, db_delete_promotion, db_get_all_promotions, db_get_promotion_by_code,
+    db_update_promotion,
+};
+use crate::models::dbpool::PgPool;
+use crate::models::promotion::{NewPromotion, Promotion, PromotionError};
+
+#[get("")]
+pub(crate) async fn get_all_promotions(
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder> {
+    let promotions = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_all_promotions(&mut conn)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(promotions))
+}
+
+#[post("/create")]
+pub(crate) async fn create_promotion(
+    pool: web::Data<PgPool>,
+    new_promotion: web::Json<NewPromotion>,
+) -> Result<impl Responder> {
+    let mut new_promotion = new_promotion.into_inner();
+    if new_promotion.code.is_none() || new_promotion.kind.is_none() || new_promotion.amount.is_none() {
+        return Ok(HttpResponse::BadRequest().body("code, kind and amount are required"));
+    }
+    new_promotion.code = new_promotion.code.map(|code| code.trim().to_uppercase());
+
+    let promotion = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_create_promotion(&mut conn, new_promotion)
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(promotion))
+}
+
+#[put("/update/{id}")]
+pub(crate) async fn update_promotion(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    new_promotion: web::Json<NewPromotion>,
+) -> Result<impl Responder> {
+    let mut new_promotion = new_promotion.into_inner();
+    new_promotion.code = new_promotion.code.map(|code| code.trim().to_uppercase());
+
+    let promotion = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_update_promotion(&mut conn, id.into_inner(), new_promotion)
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(promotion))
+}
+
+#[delete("/delete/{id}")]
+pub(crate) async fn delete_promotion(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let deleted_promotion = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_delete_promotion(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(deleted_promotion))
+}
+
+// look up a code given by a customer, an unknown code is reported the same way as one that can not be applied
+pub(crate) fn find_promotion(
+    conn: &mut PgConnection,
+    code: String,
+) -> Result<Result<Promotion, PromotionError>, diesel::result::Error> {
+    let promotion = db_get_promotion_by_code(conn, code.clone())?;
+
+    Ok(promotion.ok_or(PromotionError::NotFound { code: code.trim().to_uppercase() }))
+}
\ No newline at end of file
@@ -0,0 +1,270 @@
+use std::{collections::HashMap, str::FromStr};
+
+use actix_web::{error, get, post, web, HttpResponse, Responder, Result};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use diesel::result::Error;
+use stripe::{Charge, Client, CreateRefund, PaymentIntentId, Refund, RequestStrategy};
+
+use crate::database::returns::{
+    db_create_return, db_decide_return, db_get_return, db_get_returns, db_get_returns_by_user_id,
+    db_receive_return, db_reconcile_refunds, db_record_return_refund, db_release_return_refund,
+    db_start_return_refund,
+};
+use crate::extractors::claims::Claims;
+use crate::extractors::permissions::{OrdersFulfill, OrdersRead, OrdersRefund, Permission, RequirePermission};
+use crate::mailer::{outbox::enqueue_order_email, templates::OrderEmail};
+use crate::middleware::permission_guard::PermissionGuard;
+use crate::models::dbpool::PgPool;
+use crate::models::order_return::{ReturnDecision, ReturnQuery, ReturnRefund, ReturnRequest};
+
+// the customer asks to send back lines of their order
+#[post("/returns/request/{id}")]
+pub(crate) async fn request_return(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    request: web::Json<ReturnRequest>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let order_return = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_create_return(&mut conn, claims.sub, id.into_inner(), request.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order_return {
+        Some(Ok(order_return)) => Ok(HttpResponse::Created().json(order_return)),
+        Some(Err(return_error)) => Ok(HttpResponse::BadRequest().json(return_error)),
+        None => Ok(HttpResponse::NotFound().body("Order not found")),
+    }
+}
+
+#[get("/returns/mine")]
+pub(crate) async fn get_my_returns(
+    pool: web::Data<PgPool>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let returns = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_returns_by_user_id(&mut conn, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(returns))
+}
+
+#[get("/returns", wrap = "PermissionGuard::<OrdersRead>::new()")]
+pub(crate) async fn get_returns(
+    pool: web::Data<PgPool>,
+    query: web::Query<ReturnQuery>,
+) -> Result<impl Responder> {
+    let returns = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_returns(&mut conn, query.into_inner().status)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(returns))
+}
+
+// customers only see their own returns, staff see all of them
+#[get("/returns/id/{id}")]
+pub(crate) async fn get_return(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let staff = claims.has_permission(OrdersRead::NAME);
+
+    let order_return = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_return(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order_return {
+        Some(order_return) if staff || order_return.user_id == claims.sub => Ok(HttpResponse::Ok().json(order_return)),
+        _ => Ok(HttpResponse::NotFound().body("Return not found")),
+    }
+}
+
+#[post("/returns/approve/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
+pub(crate) async fn approve_return(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    decision: web::Json<ReturnDecision>,
+    _permission: RequirePermission<OrdersRefund>,
+) -> Result<impl Responder> {
+    decide_return(pool, id.into_inner(), true, decision.into_inner()).await
+}
+
+#[post("/returns/reject/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
+pub(crate) async fn reject_return(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    decision: web::Json<ReturnDecision>,
+    _permission: RequirePermission<OrdersRefund>,
+) -> Result<impl Responder> {
+    decide_return(pool, id.into_inner(), false, decision.into_inner()).await
+}
+
+async fn decide_return(
+    pool: web::Data<PgPool>,
+    id: String,
+    approved: bool,
+    decision: ReturnDecision,
+) -> Result<HttpResponse> {
+    let order_return = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_decide_return(&mut conn, id, approved, decision.note)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order_return {
+        Some(Ok(order_return)) => Ok(HttpResponse::Ok().json(order_return)),
+        Some(Err(return_error)) => Ok(HttpResponse::BadRequest().json(return_error)),
+        None => Ok(HttpResponse::NotFound().body("Return not found")),
+    }
+}
+
+// the returned goods arrived and are put back into the inventory
+#[post("/returns/receive/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
+pub(crate) async fn receive_return(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    _permission: RequirePermission<OrdersFulfill>,
+) -> Result<impl Responder> {
+    let order_return = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_receive_return(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order_return {
+        Some(Ok(order_return)) => Ok(HttpResponse::Ok().json(order_return)),
+        Some(Err(return_error)) => Ok(HttpResponse::BadRequest().json(return_error)),
+        None => Ok(HttpResponse::NotFound().body("Return not found")),
+    }
+}
+
+// pay back a received return through stripe, by default for what was paid for its lines
+#[post("/returns/refund/{id}", wrap = "PermissionGuard::<OrdersRead>::new()")]
+pub(crate) async fn refund_return(
+    pool: web::Data<PgPool>,
+    client: web::Data<Client>,
+    id: web::Path<String>,
+    refund: web::Json<ReturnRefund>,
+    _permission: RequirePermission<OrdersRefund>,
+) -> Result<impl Responder> {
+    let cloned_pool = pool.clone();
+    let started = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
+        db_start_return_refund(&mut conn, id.into_inner(), refund.into_inner().amount)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let (order_return, payment_intent_id) = match started {
+        Some(Ok(started)) => started,
+        Some(Err(return_error)) => return Ok(HttpResponse::BadRequest().json(return_error)),
+        None => return Ok(HttpResponse::NotFound().body("Return not found")),
+    };
+
+    let amount = order_return.refund_amount.clone().unwrap_or_default();
+    let stripe_refund = refund_payment(
+        &client,
+        &payment_intent_id,
+        Some(&amount),
+        format!("refund-{}", order_return.id),
+        HashMap::from([
+            ("order_id".to_string(), order_return.order_id.clone()),
+            ("return_id".to_string(), order_return.id.clone()),
+        ]),
+    ).await;
+
+    let stripe_refund = match stripe_refund {
+        Ok(stripe_refund) => stripe_refund,
+        Err(e) => {
+            let return_id = order_return.id.clone();
+            web::block(move || {
+                let mut conn = pool.get().unwrap();
+                db_release_return_refund(&mut conn, return_id)
+            })
+            .await?
+            .map_err(error::ErrorInternalServerError)?;
+
+            return Err(e);
+        },
+    };
+
+    let order_return = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        let order_return = db_record_return_refund(&mut conn, order_return.id, amount, stripe_refund.id.to_string())?;
+
+        if let Err(e) = enqueue_order_email(&mut conn, OrderEmail::Refunded, order_return.order_id.clone()) {
+            log::error!("failed to queue {} email for {}: {}", OrderEmail::Refunded.kind(), order_return.order_id, e);
+        }
+
+        Ok::<_, Error>(order_return)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(order_return))
+}
+
+// refund the payment of an order, all of what is left on it when no amount is given. the idempotency key
+// makes stripe answer a repeated request with the refund it already made instead of refunding twice
+pub(crate) async fn refund_payment(
+    client: &Client,
+    payment_intent_id: &str,
+    amount: Option<&BigDecimal>,
+    idempotency_key: String,
+    metadata: HashMap<String, String>,
+) -> Result<Refund> {
+    let mut params = CreateRefund::new();
+    params.payment_intent = Some(PaymentIntentId::from_str(payment_intent_id).map_err(error::ErrorInternalServerError)?);
+    params.amount = amount.and_then(|amount| (amount * BigDecimal::from(100)).to_i64());
+    params.metadata = Some(metadata);
+
+    let client = client.clone().with_strategy(RequestStrategy::Idempotent(idempotency_key));
+    Refund::create(&client, params).await.map_err(error::ErrorInternalServerError)
+}
+
+// stripe reports refunds on the charge, including ones issued from its dashboard
+pub(crate) async fn charge_refunded(
+    pool: web::Data<PgPool>,
+    charge: Charge,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let payment_intent = match charge.payment_intent {
+        Some(payment_intent) => payment_intent.id().to_string(),
+        None => return Ok(()),
+    };
+    let amount_refunded = BigDecimal::from(charge.amount_refunded) / BigDecimal::from(100);
+
+    let refunds = charge.refunds.data.into_iter()
+        .filter_map(|refund| {
+            let return_id = refund.metadata.as_ref()?.get("return_id")?.clone();
+            Some((return_id, refund.id.to_string(), BigDecimal::from(refund.amount) / BigDecimal::from(100)))
+        })
+        .collect();
+
+    let order = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_reconcile_refunds(&mut conn, payment_intent, amount_refunded, refunds)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match order {
+        Some(order) => log::info!("order {} has {} refunded", order.id, order.refunded_amount),
+        None => log::warn!("refunded charge {} does not belong to an order", charge.id),
+    }
+
+    Ok(())
+}
\ No newline at end of file
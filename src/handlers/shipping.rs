@@ -0,0 +1,202 @@
+use actix_web::{delete, error, get, post, put, web, HttpResponse, Responder, Result};
+use bigdecimal::BigDecimal;
+use diesel::PgConnection;
+
+use crate::database::carts::db_get_cart_with_products;
+use crate::database::shipping::{
+    db_count_shipping_zones, db_create_shipping_method, db_create_shipping_zone,
+    db_delete_shipping_method, db_delete_shipping_zone, db_get_active_shipping_methods,
+    db_get_shipping_zone_for_country, db_get_shipping_zones, db_update_shipping_method,
+    db_update_shipping_zone,
+};
+use crate::extractors::claims::Claims;
+use crate::extractors::permissions::ProductsWrite;
+use crate::middleware::permission_guard::PermissionGuard;
+use crate::models::cart::CartSummary;
+use crate::models::dbpool::PgPool;
+use crate::models::shipping::{NewShippingMethod, NewShippingZone, ShippingError, ShippingQuery, ShippingQuote};
+
+// what each shipping method to the given country would cost for the user's cart
+#[get("/rates")]
+pub(crate) async fn get_shipping_rates(
+    pool: web::Data<PgPool>,
+    query: web::Query<ShippingQuery>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let quotes = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        let summary = CartSummary::new(db_get_cart_with_products(&mut conn, claims.sub)?);
+        quote_shipping(&mut conn, query.country(), &summary.subtotal, &summary.package_weight)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match quotes {
+        Ok(quotes) => Ok(HttpResponse::Ok().json(quotes)),
+        Err(shipping_error) => Ok(HttpResponse::BadRequest().json(shipping_error)),
+    }
+}
+
+#[get("/zones", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn get_shipping_zones(
+    pool: web::Data<PgPool>,
+) -> Result<impl Responder> {
+    let zones = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_get_shipping_zones(&mut conn)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(zones))
+}
+
+#[post("/zones/create", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn create_shipping_zone(
+    pool: web::Data<PgPool>,
+    new_zone: web::Json<NewShippingZone>,
+) -> Result<impl Responder> {
+    let new_zone = normalize_countries(new_zone.into_inner());
+    if new_zone.name.is_none() || new_zone.countries.is_none() {
+        return Ok(HttpResponse::BadRequest().body("name and countries are required"));
+    }
+
+    let zone = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_create_shipping_zone(&mut conn, new_zone)
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(zone))
+}
+
+#[put("/zones/update/{id}", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn update_shipping_zone(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    new_zone: web::Json<NewShippingZone>,
+) -> Result<impl Responder> {
+    let new_zone = normalize_countries(new_zone.into_inner());
+
+    let zone = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_update_shipping_zone(&mut conn, id.into_inner(), new_zone)
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    Ok(HttpResponse::Ok().json(zone))
+}
+
+#[delete("/zones/delete/{id}", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn delete_shipping_zone(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let deleted_zone = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_delete_shipping_zone(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(deleted_zone))
+}
+
+#[post("/methods/create", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn create_shipping_method(
+    pool: web::Data<PgPool>,
+    new_method: web::Json<NewShippingMethod>,
+) -> Result<impl Responder> {
+    let new_method = new_method.into_inner();
+    if new_method.zone_id.is_none() || new_method.name.is_none() || new_method.kind.is_none() || new_method.amount.is_none() {
+        return Ok(HttpResponse::BadRequest().body("zone_id, name, kind and amount are required"));
+    }
+
+    let method = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_create_shipping_method(&mut conn, new_method)
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    match method {
+        Ok(method) => Ok(HttpResponse::Ok().json(method)),
+        Err(shipping_error) => Ok(HttpResponse::BadRequest().json(shipping_error)),
+    }
+}
+
+#[put("/methods/update/{id}", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn update_shipping_method(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    new_method: web::Json<NewShippingMethod>,
+) -> Result<impl Responder> {
+    let method = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_update_shipping_method(&mut conn, id.into_inner(), new_method.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorBadRequest)?;
+
+    match method {
+        Ok(method) => Ok(HttpResponse::Ok().json(method)),
+        Err(shipping_error) => Ok(HttpResponse::BadRequest().json(shipping_error)),
+    }
+}
+
+#[delete("/methods/delete/{id}", wrap = "PermissionGuard::<ProductsWrite>::new()")]
+pub(crate) async fn delete_shipping_method(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let deleted_method = web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_delete_shipping_method(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(deleted_method))
+}
+
+// the quotes of every active method of the zone serving `country`
+// with no zones set up at all nothing is charged for shipping, as before zones existed
+pub(crate) fn quote_shipping(
+    conn: &mut PgConnection,
+    country: String,
+    subtotal: &BigDecimal,
+    weight_kg: &BigDecimal,
+) -> Result<Result<Vec<ShippingQuote>, ShippingError>, diesel::result::Error> {
+    if db_count_shipping_zones(conn)? == 0 {
+        return Ok(Ok(Vec::new()));
+    }
+
+    let zone = match db_get_shipping_zone_for_country(conn, country.clone())? {
+        Some(zone) => zone,
+        None => return Ok(Err(ShippingError::NotShipped { country })),
+    };
+
+    let methods = db_get_active_shipping_methods(conn, zone.id)?;
+    if methods.is_empty() {
+        return Ok(Err(ShippingError::NoMethods { country }));
+    }
+
+    // a free_over method can end up cheaper than its base amount, so they are ordered by what they cost this cart
+    let mut quotes = methods.iter().map(|method| method.quote(subtotal, weight_kg)).collect::<Vec<ShippingQuote>>();
+    quotes.sort_by(|a, b| a.cost.cmp(&b.cost));
+
+    Ok(Ok(quotes))
+}
+
+fn normalize_countries(mut new_zone: NewShippingZone) -> NewShippingZone {
+    new_zone.countries = new_zone.countries.map(|countries| {
+        countries.into_iter()
+            .map(|country| country.map(|country| country.trim().to_uppercase()))
+            .collect()
+    });
+
+    new_zone
+}
\ No newline at end of file
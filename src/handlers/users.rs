@@ -1,46 +1,166 @@
-use std::collections::HashSet;
+use std::{collections::HashMap, str::FromStr};
 
-use actix_web::{post, Result, web, Responder, HttpResponse, error, delete, get, put};
+use actix_web::{post, Result, web, Responder, HttpResponse, HttpRequest, error, delete, get, patch, put};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use stripe::{Client, CreateCustomer, Customer, CustomerId, UpdateCustomer};
 
-use crate::{models::{dbpool::PgPool, user::{User, SubmitRoles, UserId}}, database::users::{db_create_user, db_delete_user, db_update_user, db_get_user}, extractors::claims::Claims};
+use crate::{models::{dbpool::PgPool, pagination::PageQuery, user::{DisableUser, User, ProfileUpdate, ProvisionUser, RoleError, UserId, UserSearchQuery}}, database::users::{db_delete_user, db_disable_user, db_enable_user, db_erase_user, db_export_user, db_get_user, db_get_user_overview, db_search_users, db_set_user_role, db_link_stripe_customer, db_touch_user, db_unlink_stripe_customer, db_update_user_email_by_stripe_id, db_update_user_profile, db_upsert_user}, extractors::{claims::Claims, permissions::{Permission, UsersManage, ROLES}}, middleware::permission_guard::PermissionGuard};
 
+const PROVISIONING_TIMESTAMP_HEADER: &str = "X-Provisioning-Timestamp";
+const PROVISIONING_SIGNATURE_HEADER: &str = "X-Provisioning-Signature";
+const PROVISIONING_TOLERANCE_SECONDS: i64 = 300;
 
-#[post("/add")]
-async fn create_user(
+/// the secret the auth0 action signs provisioning requests with, read once at startup
+pub(crate) struct ProvisioningSecret(String);
+
+impl ProvisioningSecret {
+    pub(crate) fn from_env() -> Self {
+        ProvisioningSecret(std::env::var("AUTH0_PROVISIONING_SECRET").expect("AUTH0_PROVISIONING_SECRET should be set"))
+    }
+}
+
+
+/// upsert a user from auth0, the request is signed by the auth0 action with `AUTH0_PROVISIONING_SECRET`
+/// and safe to repeat, a stripe customer is only created for users without one
+#[post("/provision")]
+async fn provision_user(
     pool: web::Data<PgPool>,
-    new_user: web::Json<User>,
+    client: web::Data<Client>,
+    secret: web::Data<ProvisioningSecret>,
+    req: HttpRequest,
+    payload: web::Bytes,
 ) -> Result<impl Responder> {
-    let user = web::block(move || {
+    verify_provisioning_signature(&secret, &req, &payload)?;
 
-        let mut conn = pool.get().unwrap();
+    let ProvisionUser { user_id, email, roles } = serde_json::from_slice(&payload)
+        .map_err(error::ErrorBadRequest)?;
+
+    let cloned_pool = pool.clone();
+    let (previous, user) = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
 
-        db_create_user(&mut conn, new_user.into_inner())
+        let previous = db_get_user(&mut conn, user_id.clone())?;
+        let user = db_upsert_user(&mut conn, user_id, email, roles)?;
+        Ok::<_, diesel::result::Error>((previous, user))
     })
     .await?
     .map_err(error::ErrorInternalServerError)?;
 
+    // an email changed in auth0 is passed on to stripe
+    if let (Some(previous), Some(stripe_id)) = (previous, &user.stripe_id) {
+        if previous.email != user.email {
+            update_stripe_customer_email(&client, stripe_id, &user.email).await?;
+        }
+    }
+
+    let user = ensure_stripe_customer(&pool, &client, user).await?;
+
     Ok(HttpResponse::Ok().json(user))
 }
 
-#[put("/update")]
-async fn update_user(
-    pool: web::Data<PgPool>,
-    submit_roles: web::Json<SubmitRoles>,
-) -> Result<impl Responder> {
-    let SubmitRoles { user_id, stripe_customer_id, roles } = submit_roles.into_inner();
+/// the user with a stripe customer, created and linked the first time one is needed
+pub(crate) async fn ensure_stripe_customer(pool: &web::Data<PgPool>, client: &Client, user: User) -> Result<User> {
+    if user.stripe_id.is_some() {
+        return Ok(user);
+    }
+
+    let mut create_customer = CreateCustomer::new();
+    create_customer.email = Some(&user.email);
+    create_customer.metadata = Some(HashMap::from([(String::from("user_id"), user.id.clone())]));
+
+    let customer = Customer::create(client, create_customer)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let customer_id = customer.id.to_string();
+    let cloned_pool = pool.clone();
     let user = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
+
+        db_link_stripe_customer(&mut conn, user.id, customer_id)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    // a concurrent request linked its customer first, drop the one created here
+    if user.stripe_id.as_deref() != Some(customer.id.as_str()) {
+        if let Err(e) = Customer::delete(client, &customer.id).await {
+            log::error!("Failed to delete duplicate stripe customer {}: {}", customer.id, e);
+        }
+    }
+
+    Ok(user)
+}
 
+async fn update_stripe_customer_email(client: &Client, stripe_id: &str, email: &str) -> Result<Customer> {
+    let customer_id = CustomerId::from_str(stripe_id).map_err(error::ErrorInternalServerError)?;
+    Customer::update(client, &customer_id, UpdateCustomer { email: Some(email), ..Default::default() })
+        .await
+        .map_err(error::ErrorInternalServerError)
+}
+
+/// an email changed in stripe, e.g. during checkout, is copied onto the user
+pub(crate) async fn wh_update_customer(
+    pool: web::Data<PgPool>,
+    customer: Customer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let new_email = match customer.email {
+        Some(new_email) => new_email,
+        None => return Ok(()),
+    };
+
+    web::block(move || {
         let mut conn = pool.get().unwrap();
+        db_update_user_email_by_stripe_id(&mut conn, customer.id.to_string(), new_email)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
 
-        db_update_user(&mut conn, user_id, stripe_customer_id, roles)
+    Ok(())
+}
+
+/// unlink a customer deleted in stripe, the next checkout creates a new one
+pub(crate) async fn wh_delete_customer(
+    pool: web::Data<PgPool>,
+    customer: Customer,
+) -> Result<(), Box<dyn std::error::Error>> {
+    web::block(move || {
+        let mut conn = pool.get().unwrap();
+        db_unlink_stripe_customer(&mut conn, customer.id.to_string())
     })
     .await?
     .map_err(error::ErrorInternalServerError)?;
 
-    Ok(HttpResponse::Ok().json(user))
+    Ok(())
 }
 
-#[delete("/delete")]
+/// `X-Provisioning-Signature` is the hex hmac-sha256 of `{timestamp}.{body}`, the timestamp in
+/// `X-Provisioning-Timestamp` may be five minutes off so captured requests can't be replayed later
+fn verify_provisioning_signature(secret: &ProvisioningSecret, req: &HttpRequest, payload: &[u8]) -> Result<()> {
+    let header = |name: &str| req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| error::ErrorUnauthorized(format!("Missing {} header", name)));
+
+    let timestamp = header(PROVISIONING_TIMESTAMP_HEADER)?;
+    let signature = hex::decode(header(PROVISIONING_SIGNATURE_HEADER)?)
+        .map_err(|_| error::ErrorUnauthorized("Malformed signature"))?;
+
+    let sent_at = timestamp.parse::<i64>().map_err(|_| error::ErrorUnauthorized("Malformed timestamp"))?;
+    if (chrono::Utc::now().timestamp() - sent_at).abs() > PROVISIONING_TOLERANCE_SECONDS {
+        return Err(error::ErrorUnauthorized("Timestamp outside the tolerance"));
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.0.as_bytes()).map_err(error::ErrorInternalServerError)?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+
+    mac.verify_slice(&signature).map_err(|_| error::ErrorUnauthorized("Invalid signature"))
+}
+
+#[delete("/delete", wrap = "PermissionGuard::<UsersManage>::new()")]
 async fn delete_user(
     pool: web::Data<PgPool>,
     user_id: web::Json<String>,
@@ -64,8 +184,8 @@ async fn get_user(
     claims: Claims,
 ) -> Result<impl Responder> {
     // verify the user is getting their own information
-    // if the user is an admin bypass this check
-    if !claims.validate_roles(&HashSet::from(["admin".to_string()])) {
+    // staff managing users bypass this check
+    if !claims.has_permission(UsersManage::NAME) {
         if claims.sub != user_id.id {
             return Ok(HttpResponse::Unauthorized().finish());
         }
@@ -82,6 +202,225 @@ async fn get_user(
     Ok(HttpResponse::Ok().json(user))
 }
 
+/// the profile of the logged in user
+#[get("/me")]
+async fn get_me(
+    pool: web::Data<PgPool>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let user = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_touch_user(&mut conn, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+/// change the display name, phone or marketing opt-in of the logged in user
+#[patch("/me")]
+async fn update_me(
+    pool: web::Data<PgPool>,
+    profile: web::Json<ProfileUpdate>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let changes = match profile.into_inner().into_changes() {
+        Ok(changes) => changes,
+        Err(errors) => return Ok(HttpResponse::BadRequest().json(errors)),
+    };
+
+    let user = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_update_user_profile(&mut conn, claims.sub, changes)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+/// a json archive of everything stored about the logged in user
+#[get("/me/export")]
+async fn export_me(
+    pool: web::Data<PgPool>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let export = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_export_user(&mut conn, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match export {
+        Some(export) => Ok(HttpResponse::Ok()
+            .insert_header(("Content-Disposition", "attachment; filename=\"export.json\""))
+            .json(export)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+/// erase the logged in user, orders stay without the personal data and the stripe customer is deleted
+#[delete("/me")]
+async fn erase_me(
+    pool: web::Data<PgPool>,
+    client: web::Data<Client>,
+    claims: Claims,
+) -> Result<impl Responder> {
+    let erased = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_erase_user(&mut conn, claims.sub)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let user = match erased {
+        Some(Ok(user)) => user,
+        Some(Err(e)) => return Ok(HttpResponse::Conflict().json(e)),
+        None => return Ok(HttpResponse::NotFound().body("User not found")),
+    };
+
+    // the account is already unlinked, a customer left behind only needs removing from stripe by hand
+    if let Some(stripe_id) = user.stripe_id {
+        let deleted = match CustomerId::from_str(&stripe_id) {
+            Ok(customer_id) => Customer::delete(&client, &customer_id).await.map(|_| ()).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = deleted {
+            log::error!("Failed to delete stripe customer {} of erased user {}: {}", stripe_id, user.id, e);
+        }
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// search users by part of their email or id, newest first
+#[get("/admin", wrap = "PermissionGuard::<UsersManage>::new()")]
+async fn admin_search_users(
+    pool: web::Data<PgPool>,
+    search: web::Query<UserSearchQuery>,
+    query: web::Query<PageQuery>,
+) -> Result<impl Responder> {
+    let found = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_search_users(&mut conn, search.into_inner().q, &query)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(found))
+}
+
+#[get("/admin/{id}", wrap = "PermissionGuard::<UsersManage>::new()")]
+async fn admin_get_user(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let overview = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_get_user_overview(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match overview {
+        Some(overview) => Ok(HttpResponse::Ok().json(overview)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+#[put("/admin/{id}/roles/{role}", wrap = "PermissionGuard::<UsersManage>::new()")]
+async fn admin_grant_role(
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> Result<impl Responder> {
+    set_role(pool, path.into_inner(), true).await
+}
+
+#[delete("/admin/{id}/roles/{role}", wrap = "PermissionGuard::<UsersManage>::new()")]
+async fn admin_revoke_role(
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> Result<impl Responder> {
+    set_role(pool, path.into_inner(), false).await
+}
+
+async fn set_role(
+    pool: web::Data<PgPool>,
+    (user_id, role): (String, String),
+    granted: bool,
+) -> Result<HttpResponse> {
+    if !ROLES.contains(&role.as_str()) {
+        return Ok(HttpResponse::BadRequest().json(RoleError::UnknownRole { role }));
+    }
+
+    let user = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_set_user_role(&mut conn, user_id, role, granted)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+#[post("/admin/{id}/disable", wrap = "PermissionGuard::<UsersManage>::new()")]
+async fn admin_disable_user(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+    payload: Option<web::Json<DisableUser>>,
+) -> Result<impl Responder> {
+    let reason = payload.map(|payload| payload.into_inner()).unwrap_or_default().reason;
+    let user = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_disable_user(&mut conn, id.into_inner(), reason)
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
+#[post("/admin/{id}/enable", wrap = "PermissionGuard::<UsersManage>::new()")]
+async fn admin_enable_user(
+    pool: web::Data<PgPool>,
+    id: web::Path<String>,
+) -> Result<impl Responder> {
+    let user = web::block(move || {
+        let mut conn = pool.get().unwrap();
+
+        db_enable_user(&mut conn, id.into_inner())
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    match user {
+        Some(user) => Ok(HttpResponse::Ok().json(user)),
+        None => Ok(HttpResponse::NotFound().body("User not found")),
+    }
+}
+
 #[get("/index")]
 async fn index(
     claims: Claims,
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use actix_web::web;
+use diesel::{result::Error, PgConnection};
+
+use crate::{
+    database::{
+        abandoned_carts::{db_mark_recovery_sent, db_record_abandoned_cart},
+        carts::{db_get_cart_with_products, db_get_idle_carts},
+    },
+    mailer::outbox::enqueue_cart_recovery_email,
+    models::{
+        abandoned_cart::{abandoned_after_hours, AbandonedCart, NewAbandonedCart},
+        cart::CartSummary,
+        dbpool::PgPool,
+    },
+};
+
+/// periodically record carts that have gone idle, optionally sending each customer a recovery email
+pub(crate) async fn run_abandoned_cart_job(pool: PgPool) {
+    let poll_minutes = std::env::var("CART_ABANDONED_POLL_MINUTES")
+        .map(|s| s.parse::<u64>().expect("CART_ABANDONED_POLL_MINUTES should be a number"))
+        .unwrap_or(15);
+    let send_recovery = std::env::var("CART_RECOVERY_EMAILS").map(|s| s == "true").unwrap_or(false);
+
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(poll_minutes * 60));
+    loop {
+        interval.tick().await;
+
+        let cloned_pool = pool.clone();
+        let recorded = web::block(move || {
+            let mut conn = cloned_pool.get().unwrap();
+            record_abandoned_carts(&mut conn, send_recovery)
+        })
+        .await;
+
+        match recorded {
+            Ok(Ok(recorded)) if !recorded.is_empty() => log::info!("recorded {} abandoned carts", recorded.len()),
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => log::error!("failed to record abandoned carts: {}", e),
+            Err(e) => log::error!("failed to record abandoned carts: {}", e),
+        }
+    }
+}
+
+// snapshot every cart idle past the threshold, each idle period is only recorded once
+pub(crate) fn record_abandoned_carts(
+    conn: &mut PgConnection,
+    send_recovery: bool,
+) -> Result<Vec<AbandonedCart>, Error> {
+    let idle_carts = db_get_idle_carts(conn, abandoned_after_hours())?;
+
+    let mut recorded = Vec::new();
+    for (user_id, last_activity_at) in idle_carts {
+        let last_activity_at = match last_activity_at {
+            Some(last_activity_at) => last_activity_at,
+            None => continue,
+        };
+
+        let summary = CartSummary::new(db_get_cart_with_products(conn, user_id.clone())?);
+        let products = summary.lines.iter()
+            .map(|line| (line.product_id.clone(), serde_json::Value::Number(serde_json::Number::from(line.quantity))))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+
+        let abandoned_cart = db_record_abandoned_cart(conn, NewAbandonedCart {
+            user_id,
+            products: serde_json::Value::Object(products),
+            item_count: summary.item_count,
+            value: summary.subtotal.with_scale(2),
+            last_activity_at,
+        })?;
+
+        if let Some(abandoned_cart) = abandoned_cart {
+            if send_recovery {
+                recorded.push(send_cart_recovery(conn, &abandoned_cart)?);
+            } else {
+                recorded.push(abandoned_cart);
+            }
+        }
+    }
+
+    Ok(recorded)
+}
+
+/// the recovery hook, queues an email with a link that restores the cart and remembers it was sent
+pub(crate) fn send_cart_recovery(
+    conn: &mut PgConnection,
+    abandoned_cart: &AbandonedCart,
+) -> Result<AbandonedCart, Error> {
+    enqueue_cart_recovery_email(conn, abandoned_cart)?;
+
+    db_mark_recovery_sent(conn, abandoned_cart.id.clone())
+}
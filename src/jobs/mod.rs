@@ -0,0 +1 @@
+pub mod abandoned_carts;
\ No newline at end of file
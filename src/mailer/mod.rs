@@ -0,0 +1,3 @@
+pub mod outbox;
+pub mod templates;
+pub mod transport;
\ No newline at end of file
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use actix_web::web;
+use diesel::{result::Error, PgConnection};
+
+use crate::{
+    database::{
+        emails::{db_claim_due_emails, db_enqueue_email, db_mark_email_failed, db_mark_email_sent},
+        orders::db_get_expanded_order_by_id,
+        products::db_get_multiple_products_by_id,
+        users::db_get_user,
+    },
+    mailer::{templates::{CartRecoveryEmail, OrderEmail}, transport::Mailer},
+    models::{abandoned_cart::AbandonedCart, dbpool::PgPool, email::{NewOutboxEmail, OutboxEmail}},
+};
+
+// how many emails a single pass of the worker sends
+const BATCH_SIZE: i64 = 20;
+// how long a claimed email is hidden from other workers while it is being sent
+const LEASE_SECONDS: i32 = 300;
+
+/// render an order email and store it in the outbox, the worker delivers it later
+pub(crate) fn enqueue_order_email(
+    conn: &mut PgConnection,
+    email: OrderEmail,
+    order_id: String,
+) -> Result<OutboxEmail, Error> {
+    let order = db_get_expanded_order_by_id(conn, order_id)?;
+    let user = db_get_user(conn, order.user_id.clone())?.ok_or(Error::NotFound)?;
+
+    db_enqueue_email(conn, NewOutboxEmail {
+        order_id: Some(order.id.clone()),
+        kind: Some(email.kind().to_string()),
+        recipient: Some(user.email),
+        subject: Some(email.subject(&order)),
+        body: Some(email.render(&order)),
+    })
+}
+
+/// queue the email that links a customer back to the cart they abandoned
+pub(crate) fn enqueue_cart_recovery_email(
+    conn: &mut PgConnection,
+    abandoned_cart: &AbandonedCart,
+) -> Result<OutboxEmail, Error> {
+    let user = db_get_user(conn, abandoned_cart.user_id.clone())?.ok_or(Error::NotFound)?;
+
+    let lines = abandoned_cart.lines();
+    let line_products = db_get_multiple_products_by_id(conn, lines.iter().map(|(product_id, _)| product_id.clone()).collect())?;
+    let items = lines.into_iter()
+        .filter_map(|(product_id, quantity)| {
+            let product = line_products.iter().find(|product| product.id == product_id)?;
+            Some((product.clone(), quantity))
+        })
+        .collect::<Vec<_>>();
+
+    let frontend_url = std::env::var("CLIENT_URL").expect("CLIENT_URL must be set");
+    let restore_link = format!("{}/cart/restore/{}", frontend_url, abandoned_cart.id);
+
+    db_enqueue_email(conn, NewOutboxEmail {
+        order_id: None,
+        kind: Some(CartRecoveryEmail::KIND.to_string()),
+        recipient: Some(user.email),
+        subject: Some(CartRecoveryEmail::subject()),
+        body: Some(CartRecoveryEmail::render(&items, &restore_link)),
+    })
+}
+
+/// poll the outbox forever, sending due emails and rescheduling failures with exponential backoff
+pub(crate) async fn run_outbox_worker(pool: PgPool, mailer: Mailer) {
+    let poll_seconds = std::env::var("MAIL_POLL_SECONDS")
+        .map(|s| s.parse::<u64>().expect("MAIL_POLL_SECONDS should be a number"))
+        .unwrap_or(10);
+    let max_attempts = std::env::var("MAIL_MAX_ATTEMPTS")
+        .map(|s| s.parse::<i32>().expect("MAIL_MAX_ATTEMPTS should be a number"))
+        .unwrap_or(5);
+
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(poll_seconds));
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = process_outbox(pool.clone(), &mailer, max_attempts).await {
+            log::error!("failed to process email outbox: {}", e);
+        }
+    }
+}
+
+async fn process_outbox(
+    pool: PgPool,
+    mailer: &Mailer,
+    max_attempts: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cloned_pool = pool.clone();
+    let due_emails = web::block(move || {
+        let mut conn = cloned_pool.get().unwrap();
+        db_claim_due_emails(&mut conn, BATCH_SIZE, LEASE_SECONDS)
+    })
+    .await??;
+
+    for email in due_emails {
+        let result = mailer.send(&email).await.map_err(|e| e.to_string());
+
+        let cloned_pool = pool.clone();
+        let email = web::block(move || {
+            let mut conn = cloned_pool.get().unwrap();
+
+            match result {
+                Ok(()) => db_mark_email_sent(&mut conn, email.id),
+                Err(e) => {
+                    let retry_in_seconds = retry_in_seconds(email.attempts, max_attempts);
+                    db_mark_email_failed(&mut conn, email.id, e, retry_in_seconds)
+                }
+            }
+        })
+        .await??;
+
+        match email.status.as_str() {
+            "sent" => log::info!("sent {} email {} to {}", email.kind, email.id, email.recipient),
+            _ => log::warn!("failed to send {} email {} to {}: {:?}", email.kind, email.id, email.recipient, email.last_error),
+        }
+    }
+
+    Ok(())
+}
+
+// back off 1, 2, 4, 8... minutes after a failed attempt until we run out of attempts
+fn retry_in_seconds(attempts: i32, max_attempts: i32) -> Option<i32> {
+    if attempts + 1 < max_attempts {
+        Some(60 * 2_i32.pow(attempts as u32))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::retry_in_seconds;
+
+    #[test]
+    fn failed_sends_back_off_exponentially() {
+        let schedule = (0..5).map(|attempts| retry_in_seconds(attempts, 5)).collect::<Vec<_>>();
+
+        assert_eq!(schedule, vec![Some(60), Some(120), Some(240), Some(480), None]);
+    }
+
+    #[test]
+    fn last_attempt_is_not_retried() {
+        assert_eq!(retry_in_seconds(0, 1), None);
+        assert_eq!(retry_in_seconds(2, 3), None);
+    }
+}
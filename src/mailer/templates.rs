@@ -0,0 +1,256 @@
+use bigdecimal::BigDecimal;
+
+use crate::models::{order::ExpandedOrder, product::Product};
+
+// the transactional emails sent over the lifetime of an order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OrderEmail {
+    Confirmation,
+    Shipped,
+    Delivered,
+    Canceled,
+    Refunded,
+}
+
+impl OrderEmail {
+    // stored in the `kind` column of the outbox
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            OrderEmail::Confirmation => "order_confirmation",
+            OrderEmail::Shipped => "order_shipped",
+            OrderEmail::Delivered => "order_delivered",
+            OrderEmail::Canceled => "order_canceled",
+            OrderEmail::Refunded => "order_refunded",
+        }
+    }
+
+    // map an order status to the email the customer should receive when the order moves into it
+    pub(crate) fn from_status(status: &str) -> Option<Self> {
+        match status {
+            "shipped" => Some(OrderEmail::Shipped),
+            "delievered" => Some(OrderEmail::Delivered),
+            "canceled" => Some(OrderEmail::Canceled),
+            "returned" => Some(OrderEmail::Refunded),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn subject(&self, order: &ExpandedOrder) -> String {
+        match self {
+            OrderEmail::Confirmation => format!("We received your order {}", order.id),
+            OrderEmail::Shipped => format!("Your order {} has shipped", order.id),
+            OrderEmail::Delivered => format!("Your order {} was delivered", order.id),
+            OrderEmail::Canceled => format!("Your order {} was canceled", order.id),
+            OrderEmail::Refunded => format!("Your refund for order {} is on its way", order.id),
+        }
+    }
+
+    pub(crate) fn render(&self, order: &ExpandedOrder) -> String {
+        let intro = match self {
+            OrderEmail::Confirmation => "Thank you for your order! We are getting it ready and will let you know when it ships.",
+            OrderEmail::Shipped => "Good news, your order is on its way to you.",
+            OrderEmail::Delivered => "Your order has been delivered. We hope you enjoy it!",
+            OrderEmail::Canceled => "Your order has been canceled. If you were charged, the payment will be returned to you.",
+            OrderEmail::Refunded => "We have processed a refund for your order. It can take 5-10 business days to appear on your statement.",
+        };
+
+        let mut body = format!(
+            "Hi {},\n\n{}\n\nOrder: {}\nPlaced: {}\n\n{}\n\nShipping to:\n{}\n",
+            order.name,
+            intro,
+            order.id,
+            order.created_at.format("%B %-d, %Y"),
+            render_items(order),
+            order.address.lines().join("\n"),
+        );
+
+        // the package that just went out is the latest shipment
+        if *self == OrderEmail::Shipped {
+            if let Some(shipment) = order.shipments.last() {
+                body.push_str(&format!("\nCarrier: {}\n", shipment.carrier));
+                if let Some(tracking_number) = &shipment.tracking_number {
+                    body.push_str(&format!("Tracking number: {}\n", tracking_number));
+                }
+                if let Some(tracking_url) = &shipment.tracking_url {
+                    body.push_str(&format!("Track your package: {}\n", tracking_url));
+                }
+            }
+        }
+
+        body
+    }
+}
+
+fn render_items(order: &ExpandedOrder) -> String {
+    let mut total = BigDecimal::from(0);
+    let mut lines = order.products.iter()
+        .map(|item| {
+            let price = item.product.price.clone().unwrap_or_default();
+            let line_total = price.clone() * BigDecimal::from(item.quantity);
+            total += line_total.clone();
+            format!("{} x {} @ ${} = ${}", item.quantity, item.product.name, price.with_scale(2), line_total.with_scale(2))
+        })
+        .collect::<Vec<String>>();
+
+    if let Some(discount) = &order.discount {
+        lines.push(format!("Discount ({}): -${}", order.promotion_code.clone().unwrap_or_default(), discount.with_scale(2)));
+        total -= discount.clone();
+    }
+
+    if let Some(shipping_cost) = &order.shipping_cost {
+        lines.push(format!("Shipping ({}): ${}", order.shipping_method.clone().unwrap_or_default(), shipping_cost.with_scale(2)));
+        total += shipping_cost.clone();
+    }
+
+    if let Some(store_credit) = &order.store_credit {
+        lines.push(format!("Store credit: -${}", store_credit.with_scale(2)));
+        total -= store_credit.clone();
+    }
+
+    lines.push(format!("Total: ${}", total.with_scale(2)));
+    lines.join("\n")
+}
+
+// sent to a customer that left items in their cart, the link restores the cart when they come back
+pub(crate) struct CartRecoveryEmail;
+
+impl CartRecoveryEmail {
+    pub(crate) const KIND: &'static str = "cart_recovery";
+
+    pub(crate) fn subject() -> String {
+        "You left something in your cart".to_string()
+    }
+
+    pub(crate) fn render(items: &[(Product, i32)], restore_link: &str) -> String {
+        let lines = items.iter()
+            .map(|(product, quantity)| format!("{} x {}", quantity, product.name))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        format!(
+            "Hi,\n\nYou left these items in your cart:\n\n{}\n\nPick up where you left off:\n{}\n",
+            lines,
+            restore_link,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDate;
+
+    use crate::models::{address::ShippingAddress, order::{ExpandedOrder, OrderProduct}, product::Product, shipment::Shipment};
+
+    use super::{CartRecoveryEmail, OrderEmail};
+
+    fn order(shipments: Vec<Shipment>) -> ExpandedOrder {
+        let created_at = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap().and_hms_opt(12, 0, 0).unwrap();
+
+        ExpandedOrder {
+            id: "order-1".to_string(),
+            user_id: "auth0|1".to_string(),
+            products: vec![OrderProduct {
+                product: Product { name: "Shirt".to_string(), price: Some(BigDecimal::from(25)), ..Default::default() },
+                quantity: 2,
+            }],
+            status: "processing".to_string(),
+            name: "Ada Lovelace".to_string(),
+            address: ShippingAddress {
+                name: "Ada Lovelace".to_string(),
+                phone: None,
+                line1: Some("1 Main St".to_string()),
+                line2: None,
+                city: Some("Springfield".to_string()),
+                state: Some("IL".to_string()),
+                postal_code: Some("62701".to_string()),
+                country: Some("US".to_string()),
+            },
+            created_at,
+            updated_at: created_at,
+            promotion_code: Some("SPRING".to_string()),
+            discount: Some(BigDecimal::from(10)),
+            store_credit: Some(BigDecimal::from_str("4.50").unwrap()),
+            shipping_method_id: None,
+            shipping_method: Some("Standard".to_string()),
+            shipping_cost: Some(BigDecimal::from(5)),
+            amount_paid: BigDecimal::from_str("40.50").unwrap(),
+            refunded_amount: BigDecimal::from(0),
+            canceled_by: None,
+            canceled_at: None,
+            shipments,
+        }
+    }
+
+    fn shipment(carrier: &str, tracking_number: &str) -> Shipment {
+        let shipped_at = NaiveDate::from_ymd_opt(2024, 3, 6).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+        Shipment {
+            id: format!("shipment-{}", tracking_number),
+            order_id: "order-1".to_string(),
+            products: serde_json::json!({}),
+            carrier: carrier.to_string(),
+            tracking_number: Some(tracking_number.to_string()),
+            tracking_url: Some(format!("https://track.example.com/{}", tracking_number)),
+            shipped_at,
+            created_by: "auth0|staff".to_string(),
+            created_at: shipped_at,
+        }
+    }
+
+    #[test]
+    fn from_status() {
+        assert_eq!(OrderEmail::from_status("shipped"), Some(OrderEmail::Shipped));
+        assert_eq!(OrderEmail::from_status("delievered"), Some(OrderEmail::Delivered));
+        assert_eq!(OrderEmail::from_status("canceled"), Some(OrderEmail::Canceled));
+        assert_eq!(OrderEmail::from_status("returned"), Some(OrderEmail::Refunded));
+        assert_eq!(OrderEmail::from_status("processing"), None);
+        assert_eq!(OrderEmail::from_status("partially_shipped"), None);
+    }
+
+    #[test]
+    fn confirmation_lists_the_order() {
+        let order = order(vec![]);
+
+        assert_eq!(OrderEmail::Confirmation.subject(&order), "We received your order order-1");
+        assert_eq!(
+            OrderEmail::Confirmation.render(&order),
+            "Hi Ada Lovelace,\n\n\
+            Thank you for your order! We are getting it ready and will let you know when it ships.\n\n\
+            Order: order-1\n\
+            Placed: March 5, 2024\n\n\
+            2 x Shirt @ $25.00 = $50.00\n\
+            Discount (SPRING): -$10.00\n\
+            Shipping (Standard): $5.00\n\
+            Store credit: -$4.50\n\
+            Total: $40.50\n\n\
+            Shipping to:\n\
+            Ada Lovelace\n\
+            1 Main St\n\
+            Springfield IL 62701\n\
+            US\n",
+        );
+    }
+
+    #[test]
+    fn shipped_email_tracks_the_latest_package() {
+        let order = order(vec![shipment("UPS", "1Z001"), shipment("USPS", "9400")]);
+        let body = OrderEmail::Shipped.render(&order);
+
+        assert!(body.ends_with("\nCarrier: USPS\nTracking number: 9400\nTrack your package: https://track.example.com/9400\n"));
+        assert!(!body.contains("1Z001"));
+        assert!(!OrderEmail::Confirmation.render(&order).contains("Carrier"));
+    }
+
+    #[test]
+    fn cart_recovery_links_back_to_the_cart() {
+        let items = vec![(Product { name: "Shirt".to_string(), ..Default::default() }, 2)];
+
+        assert_eq!(
+            CartRecoveryEmail::render(&items, "https://shop.example.com/cart/restore/cart-1"),
+            "Hi,\n\nYou left these items in your cart:\n\n2 x Shirt\n\nPick up where you left off:\nhttps://shop.example.com/cart/restore/cart-1\n",
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::models::email::OutboxEmail;
+
+#[derive(Clone)]
+pub(crate) enum Mailer {
+    Smtp {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+    },
+    // without smtp settings emails are only logged, so development doesn't need a mail server
+    Log,
+}
+
+impl Mailer {
+    // build the smtp transport from the environment
+    // SMTP_TLS can be "tls", "starttls" (default) or "none", use "none" with a local catcher like mailpit
+    pub(crate) fn from_env() -> Self {
+        let host = match std::env::var("SMTP_HOST") {
+            Ok(host) => host,
+            Err(_) => {
+                log::warn!("SMTP_HOST is not set, emails are logged instead of sent");
+                return Mailer::Log;
+            },
+        };
+        let tls = std::env::var("SMTP_TLS").unwrap_or("starttls".to_string());
+
+        let mut builder = match tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).expect("SMTP_HOST should be a valid host"),
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).expect("SMTP_HOST should be a valid host"),
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            _ => panic!("SMTP_TLS should be one of tls, starttls or none"),
+        };
+
+        if let Ok(port) = std::env::var("SMTP_PORT") {
+            builder = builder.port(port.parse::<u16>().expect("SMTP_PORT should be a valid port"));
+        }
+
+        if let (Ok(username), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let from = std::env::var("MAIL_FROM")
+            .expect("MAIL_FROM should be set with SMTP_HOST")
+            .parse::<Mailbox>()
+            .expect("MAIL_FROM should be a valid mailbox");
+
+        Mailer::Smtp {
+            transport: builder.build(),
+            from,
+        }
+    }
+
+    pub(crate) async fn send(&self, email: &OutboxEmail) -> Result<(), Box<dyn std::error::Error>> {
+        let (transport, from) = match self {
+            Mailer::Smtp { transport, from } => (transport, from),
+            Mailer::Log => {
+                log::info!("not sending {} email {} to {}: {}", email.kind, email.id, email.recipient, email.subject);
+                return Ok(());
+            },
+        };
+
+        let message = Message::builder()
+            .from(from.clone())
+            .to(email.recipient.parse::<Mailbox>()?)
+            .subject(email.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body.clone())?;
+
+        transport.send(message).await?;
+
+        Ok(())
+    }
+}
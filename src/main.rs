@@ -1,5 +1,6 @@
 mod database;
 mod handlers;
+mod jobs;
 mod middleware;
 mod models;
 mod routes;
@@ -8,6 +9,7 @@ mod schema;
 mod utils;
 mod stripe;
 mod extractors;
+mod mailer;
 
 use crate::server::server;
 
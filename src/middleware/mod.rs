@@ -0,0 +1 @@
+pub mod permission_guard;
\ No newline at end of file
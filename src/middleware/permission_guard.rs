@@ -0,0 +1,68 @@
+use std::{future::{ready, Future, Ready}, marker::PhantomData, pin::Pin, rc::Rc};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, FromRequest,
+};
+
+use crate::extractors::permissions::{Permission, RequirePermission};
+
+/// wraps a route so it needs a token with the permission `P`, checked before any of its other extractors run
+pub(crate) struct PermissionGuard<P: Permission> {
+    permission: PhantomData<P>,
+}
+
+impl<P: Permission> PermissionGuard<P> {
+    pub(crate) fn new() -> Self {
+        PermissionGuard { permission: PhantomData }
+    }
+}
+
+impl<S, B, P> Transform<S, ServiceRequest> for PermissionGuard<P>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    P: Permission + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = PermissionGuardMiddleware<S, P>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(PermissionGuardMiddleware { service: Rc::new(service), permission: PhantomData }))
+    }
+}
+
+pub(crate) struct PermissionGuardMiddleware<S, P> {
+    service: Rc<S>,
+    permission: PhantomData<P>,
+}
+
+impl<S, B, P> Service<ServiceRequest> for PermissionGuardMiddleware<S, P>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+    P: Permission + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        // the same check as the extractor, answered before the handler's own extractors run
+        let guard = RequirePermission::<P>::extract(req.request());
+
+        Box::pin(async move {
+            match guard.await {
+                Ok(_) => service.call(req).await.map(ServiceResponse::map_into_left_body),
+                Err(e) => Ok(req.error_response(e).map_into_right_body()),
+            }
+        })
+    }
+}
\ No newline at end of file
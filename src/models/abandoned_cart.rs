@@ -0,0 +1,82 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::abandoned_carts;
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = abandoned_carts)]
+pub(crate) struct AbandonedCart {
+    pub(crate) id: String,
+    pub(crate) user_id: String,
+    pub(crate) products: serde_json::Value,
+    pub(crate) item_count: i32,
+    pub(crate) value: BigDecimal,
+    pub(crate) last_activity_at: NaiveDateTime,
+    pub(crate) detected_at: NaiveDateTime,
+    pub(crate) recovery_sent_at: Option<NaiveDateTime>,
+    pub(crate) recovered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = abandoned_carts)]
+pub(crate) struct NewAbandonedCart {
+    pub(crate) user_id: String,
+    pub(crate) products: serde_json::Value,
+    pub(crate) item_count: i32,
+    pub(crate) value: BigDecimal,
+    pub(crate) last_activity_at: NaiveDateTime,
+}
+
+impl AbandonedCart {
+    // the (product_id, quantity) lines that were in the cart when it was abandoned
+    pub(crate) fn lines(&self) -> Vec<(String, i32)> {
+        self.products.as_object()
+            .map(|products| {
+                products.iter()
+                    .filter_map(|(product_id, quantity)| Some((product_id.clone(), quantity.as_i64()? as i32)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AbandonedCartQuery {
+    pub(crate) since: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AbandonedCartReport {
+    pub(crate) abandoned_count: i64,
+    pub(crate) abandoned_value: BigDecimal,
+    pub(crate) recovery_sent_count: i64,
+    pub(crate) recovered_count: i64,
+    pub(crate) recovered_value: BigDecimal,
+    pub(crate) carts: Vec<AbandonedCart>,
+}
+
+impl AbandonedCartReport {
+    pub(crate) fn new(
+        carts: Vec<AbandonedCart>,
+    ) -> Self {
+        let recovered = carts.iter().filter(|cart| cart.recovered_at.is_some());
+
+        Self {
+            abandoned_count: carts.len() as i64,
+            abandoned_value: carts.iter().map(|cart| cart.value.clone()).sum(),
+            recovery_sent_count: carts.iter().filter(|cart| cart.recovery_sent_at.is_some()).count() as i64,
+            recovered_count: recovered.clone().count() as i64,
+            recovered_value: recovered.map(|cart| cart.value.clone()).sum(),
+            carts,
+        }
+    }
+}
+
+// how long a cart has to sit untouched before it counts as abandoned
+pub(crate) fn abandoned_after_hours() -> i32 {
+    std::env::var("CART_ABANDONED_AFTER_HOURS")
+        .map(|s| s.parse::<i32>().expect("CART_ABANDONED_AFTER_HOURS should be a number"))
+        .unwrap_or(24)
+}
\ No newline at end of file
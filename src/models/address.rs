@@ -0,0 +1,195 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::{Insertable, Queryable}, AsChangeset};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::user_addresses;
+
+// where an order is sent and who receives it
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct ShippingAddress {
+    pub(crate) name: String,
+    pub(crate) phone: Option<String>,
+    pub(crate) line1: Option<String>,
+    pub(crate) line2: Option<String>,
+    pub(crate) city: Option<String>,
+    pub(crate) state: Option<String>,
+    pub(crate) postal_code: Option<String>,
+    pub(crate) country: Option<String>,
+}
+
+// a field of an address that can not be shipped to
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct AddressError {
+    pub(crate) field: &'static str,
+    pub(crate) reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AddressValidationErrors {
+    pub(crate) errors: Vec<AddressError>,
+}
+
+impl ShippingAddress {
+    // trims every field and drops the ones left empty, country and state codes are upper cased
+    pub(crate) fn normalize(self) -> Self {
+        let clean = |value: Option<String>| value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+
+        Self {
+            name: self.name.trim().to_string(),
+            phone: clean(self.phone),
+            line1: clean(self.line1),
+            line2: clean(self.line2),
+            city: clean(self.city),
+            state: clean(self.state).map(|state| state.to_uppercase()),
+            postal_code: clean(self.postal_code),
+            country: clean(self.country).map(|country| country.to_uppercase()),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Vec<AddressError> {
+        let mut errors = Vec::new();
+
+        if self.name.is_empty() {
+            errors.push(AddressError { field: "name", reason: "required" });
+        }
+        if self.line1.is_none() {
+            errors.push(AddressError { field: "line1", reason: "required" });
+        }
+        if self.city.is_none() {
+            errors.push(AddressError { field: "city", reason: "required" });
+        }
+
+        match self.country.as_deref() {
+            None => errors.push(AddressError { field: "country", reason: "required" }),
+            Some(country) if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) => {
+                errors.push(AddressError { field: "country", reason: "must be a two letter country code" });
+            },
+            _ => (),
+        }
+
+        match self.postal_code.as_deref() {
+            None => errors.push(AddressError { field: "postal_code", reason: "required" }),
+            Some(postal_code) if self.country.as_deref() == Some("US") && !is_zip_code(postal_code) => {
+                errors.push(AddressError { field: "postal_code", reason: "must be a 5 digit ZIP code or ZIP+4" });
+            },
+            _ => (),
+        }
+
+        if self.country.as_deref() == Some("US") {
+            match self.state.as_deref() {
+                None => errors.push(AddressError { field: "state", reason: "required" }),
+                Some(state) if state.len() != 2 || !state.chars().all(|c| c.is_ascii_alphabetic()) => {
+                    errors.push(AddressError { field: "state", reason: "must be a two letter state code" });
+                },
+                _ => (),
+            }
+        }
+
+        if let Some(phone) = &self.phone {
+            if !is_phone_number(phone) {
+                errors.push(AddressError { field: "phone", reason: "must be a phone number of 7 to 15 digits" });
+            }
+        }
+
+        errors
+    }
+
+    // the address as it is printed on a label, missing parts are skipped
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let locality = [&self.city, &self.state, &self.postal_code].into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        [Some(self.name.clone()), self.line1.clone(), self.line2.clone(), Some(locality), self.country.clone()].into_iter()
+            .flatten()
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
+
+pub(crate) fn is_phone_number(phone: &str) -> bool {
+    let digits = phone.chars().filter(|c| c.is_ascii_digit()).count();
+    let allowed = phone.chars().all(|c| c.is_ascii_digit() || " +-().".contains(c));
+    allowed && (7..=15).contains(&digits)
+}
+
+fn is_zip_code(postal_code: &str) -> bool {
+    let (zip, plus_four) = match postal_code.split_once('-') {
+        Some((zip, plus_four)) => (zip, Some(plus_four)),
+        None => (postal_code, None),
+    };
+
+    zip.len() == 5 && zip.chars().all(|c| c.is_ascii_digit())
+        && plus_four.map(|plus_four| plus_four.len() == 4 && plus_four.chars().all(|c| c.is_ascii_digit())).unwrap_or(true)
+}
+
+// an address saved in a user's address book
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = user_addresses)]
+pub(crate) struct UserAddress {
+    pub(crate) id: String,
+    pub(crate) user_id: String,
+    pub(crate) label: Option<String>,
+    pub(crate) name: String,
+    pub(crate) phone: Option<String>,
+    pub(crate) line1: String,
+    pub(crate) line2: Option<String>,
+    pub(crate) city: String,
+    pub(crate) state: Option<String>,
+    pub(crate) postal_code: String,
+    pub(crate) country: String,
+    pub(crate) is_default_shipping: bool,
+    pub(crate) is_default_billing: bool,
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) updated_at: NaiveDateTime,
+}
+
+// the defaults are not part of the changeset, they are moved between addresses separately
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = user_addresses, treat_none_as_null = true)]
+pub(crate) struct NewUserAddress {
+    pub(crate) user_id: String,
+    pub(crate) label: Option<String>,
+    pub(crate) name: String,
+    pub(crate) phone: Option<String>,
+    pub(crate) line1: String,
+    pub(crate) line2: Option<String>,
+    pub(crate) city: String,
+    pub(crate) state: Option<String>,
+    pub(crate) postal_code: String,
+    pub(crate) country: String,
+}
+
+impl NewUserAddress {
+    // expects an address that passed validation
+    pub(crate) fn new(
+        user_id: String,
+        label: Option<String>,
+        address: ShippingAddress,
+    ) -> Self {
+        Self {
+            user_id,
+            label: label.map(|label| label.trim().to_string()).filter(|label| !label.is_empty()),
+            name: address.name,
+            phone: address.phone,
+            line1: address.line1.unwrap_or_default(),
+            line2: address.line2,
+            city: address.city.unwrap_or_default(),
+            state: address.state,
+            postal_code: address.postal_code.unwrap_or_default(),
+            country: address.country.unwrap_or_default(),
+        }
+    }
+}
+
+// leaving a default out keeps it as it is, the first address saved becomes both defaults
+#[derive(Debug, Deserialize)]
+pub(crate) struct UserAddressPayload {
+    pub(crate) label: Option<String>,
+    #[serde(flatten)]
+    pub(crate) address: ShippingAddress,
+    pub(crate) default_shipping: Option<bool>,
+    pub(crate) default_billing: Option<bool>,
+}
\ No newline at end of file
@@ -0,0 +1,104 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{extractors::{claims::Claims, permissions::ALL_PERMISSIONS}, schema::api_keys};
+
+const API_KEY_PREFIX: &str = "evk_";
+
+// a long lived credential for server to server integrations, it grants exactly its scopes
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = api_keys)]
+pub(crate) struct ApiKey {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) prefix: String,
+    pub(crate) scopes: Vec<Option<String>>,
+    pub(crate) created_by: String,
+    pub(crate) expires_at: Option<NaiveDateTime>,
+    pub(crate) last_used_at: Option<NaiveDateTime>,
+    pub(crate) revoked_at: Option<NaiveDateTime>,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = api_keys)]
+pub(crate) struct NewApiKey {
+    pub(crate) name: String,
+    pub(crate) prefix: String,
+    pub(crate) key_hash: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) created_by: String,
+    pub(crate) expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApiKeyRequest {
+    pub(crate) name: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) expires_at: Option<NaiveDateTime>,
+}
+
+// the key is only ever shown in this response
+#[derive(Debug, Serialize)]
+pub(crate) struct CreatedApiKey {
+    #[serde(flatten)]
+    pub(crate) api_key: ApiKey,
+    pub(crate) key: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum ApiKeyError {
+    MissingName,
+    UnknownScope { scope: String },
+    ScopeNotHeld { scope: String },
+    AlreadyExpired,
+}
+
+impl ApiKeyRequest {
+    // scopes are the permission names staff tokens carry, nobody can hand out a permission they don't hold themselves
+    pub(crate) fn validate(&self, creator: &Claims) -> Result<(), ApiKeyError> {
+        if self.name.trim().is_empty() {
+            return Err(ApiKeyError::MissingName);
+        }
+        if let Some(scope) = self.scopes.iter().find(|scope| !ALL_PERMISSIONS.contains(&scope.as_str())) {
+            return Err(ApiKeyError::UnknownScope { scope: scope.clone() });
+        }
+        if let Some(scope) = self.scopes.iter().find(|scope| !creator.has_permission(scope)) {
+            return Err(ApiKeyError::ScopeNotHeld { scope: scope.clone() });
+        }
+        if self.expires_at.map(|expires_at| expires_at <= chrono::Utc::now().naive_utc()).unwrap_or(false) {
+            return Err(ApiKeyError::AlreadyExpired);
+        }
+
+        Ok(())
+    }
+}
+
+impl NewApiKey {
+    /// a fresh random key and the row storing its hash
+    pub(crate) fn generate(request: ApiKeyRequest, created_by: String) -> (String, Self) {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let key = format!("{}{}", API_KEY_PREFIX, hex::encode(secret));
+
+        let new_api_key = NewApiKey {
+            name: request.name.trim().to_string(),
+            prefix: key[..API_KEY_PREFIX.len() + 8].to_string(),
+            key_hash: hash_api_key(&key),
+            scopes: request.scopes,
+            created_by,
+            expires_at: request.expires_at,
+        };
+
+        (key, new_api_key)
+    }
+}
+
+/// keys are long and random, so a plain sha-256 is enough to keep them from being read back
+pub(crate) fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
\ No newline at end of file
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
+use bigdecimal::BigDecimal;
 use diesel::{prelude::{Queryable, Insertable, Associations, Identifiable}, AsChangeset};
 use serde::{Serialize, Deserialize};
 
-use crate::models::{user::User, product::Product};
+use crate::models::{user::User, product::Product, promotion::{AppliedPromotion, Promotion, PromotionError}};
 use crate::schema::carts;
 
 #[derive(Debug, Clone, Serialize, Associations, Identifiable, Deserialize, Queryable, Insertable)]
@@ -15,6 +16,8 @@ pub(crate) struct CartItem {
     user_id: String,
     pub(crate) product_id: String,
     pub(crate) quantity: i32,
+    pub(crate) created_at: chrono::NaiveDateTime,
+    pub(crate) updated_at: chrono::NaiveDateTime,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable, AsChangeset)]
@@ -25,8 +28,184 @@ pub(crate) struct NewCartItem {
     pub(crate) quantity: i32,
 }
 
+// a single cart line sent by the client, the owner always comes from the token
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct CartItemSubmit {
+    pub(crate) product_id: String,
+    pub(crate) quantity: i32,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct CartSubmit {
-    pub(crate) user_id: String,
     pub(crate) cart: HashMap<String, i32>,
+}
+
+// why a cart line was rejected, serialized as {"product_id": ..., "error": "insufficient_stock", ...}
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum CartLineError {
+    UnknownProduct { product_id: String },
+    InactiveProduct { product_id: String },
+    InvalidQuantity { product_id: String, quantity: i32 },
+    OverMaxQuantity { product_id: String, quantity: i32, max: i32 },
+    InsufficientStock { product_id: String, quantity: i32, available: i32 },
+}
+
+impl CartLineError {
+    pub(crate) fn product_id(&self) -> &str {
+        match self {
+            CartLineError::UnknownProduct { product_id }
+            | CartLineError::InactiveProduct { product_id }
+            | CartLineError::InvalidQuantity { product_id, .. }
+            | CartLineError::OverMaxQuantity { product_id, .. }
+            | CartLineError::InsufficientStock { product_id, .. } => product_id,
+        }
+    }
+
+    // check a single line against the product it refers to, returning the first problem found
+    pub(crate) fn check(
+        product_id: &str,
+        quantity: i32,
+        product: Option<&Product>,
+    ) -> Option<Self> {
+        let product_id = product_id.to_string();
+        let max = max_line_quantity();
+
+        let product = match product {
+            Some(product) => product,
+            None => return Some(CartLineError::UnknownProduct { product_id }),
+        };
+
+        if !product.active {
+            return Some(CartLineError::InactiveProduct { product_id });
+        }
+
+        if quantity <= 0 {
+            return Some(CartLineError::InvalidQuantity { product_id, quantity });
+        }
+
+        if quantity > max {
+            return Some(CartLineError::OverMaxQuantity { product_id, quantity, max });
+        }
+
+        let available = product.inventory.unwrap_or(0);
+        if quantity > available {
+            return Some(CartLineError::InsufficientStock { product_id, quantity, available });
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CartValidationErrors {
+    pub(crate) errors: Vec<CartLineError>,
+}
+
+// the most of a single product allowed in one cart
+pub(crate) fn max_line_quantity() -> i32 {
+    std::env::var("CART_MAX_LINE_QUANTITY")
+        .map(|s| s.parse::<i32>().expect("CART_MAX_LINE_QUANTITY should be a number"))
+        .unwrap_or(10)
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CartSummaryLine {
+    pub(crate) product_id: String,
+    pub(crate) name: String,
+    pub(crate) category: Option<String>,
+    pub(crate) image: Option<String>,
+    pub(crate) unit_price: BigDecimal,
+    pub(crate) quantity: i32,
+    pub(crate) line_total: BigDecimal,
+}
+
+// a cart priced against the current products, lines listed in `problems` are not counted in the subtotal
+// `total` is the subtotal less the discount of the promotion code, if one was given and could be applied
+// `package_weight` is in kilograms, products without a weight add nothing to it
+#[derive(Debug, Serialize)]
+pub(crate) struct CartSummary {
+    pub(crate) lines: Vec<CartSummaryLine>,
+    pub(crate) subtotal: BigDecimal,
+    pub(crate) item_count: i32,
+    pub(crate) package_weight: BigDecimal,
+    pub(crate) problems: Vec<CartLineError>,
+    pub(crate) promotion: Option<AppliedPromotion>,
+    pub(crate) promotion_error: Option<PromotionError>,
+    pub(crate) total: BigDecimal,
+}
+
+impl CartSummary {
+    pub(crate) fn new(
+        cart: Vec<(CartItem, Product)>,
+    ) -> Self {
+        let mut subtotal = BigDecimal::from(0);
+        let mut item_count = 0;
+        let mut package_weight = BigDecimal::from(0);
+        let mut problems = Vec::new();
+
+        let lines = cart.into_iter()
+            .map(|(cart_item, product)| {
+                let unit_price = product.price.clone().unwrap_or_default();
+                let line_total = unit_price.clone() * BigDecimal::from(cart_item.quantity);
+
+                match CartLineError::check(&cart_item.product_id, cart_item.quantity, Some(&product)) {
+                    Some(problem) => problems.push(problem),
+                    None => {
+                        subtotal += line_total.clone();
+                        item_count += cart_item.quantity;
+                        package_weight += product.weight_kg().unwrap_or_default() * BigDecimal::from(cart_item.quantity);
+                    }
+                }
+
+                CartSummaryLine {
+                    product_id: cart_item.product_id,
+                    name: product.name,
+                    category: product.category,
+                    image: product.images.and_then(|images| images.into_iter().flatten().next()),
+                    unit_price,
+                    quantity: cart_item.quantity,
+                    line_total,
+                }
+            })
+            .collect::<Vec<CartSummaryLine>>();
+
+        Self {
+            lines,
+            subtotal: subtotal.clone(),
+            item_count,
+            package_weight: package_weight.with_scale_round(3, bigdecimal::RoundingMode::HalfUp),
+            problems,
+            promotion: None,
+            promotion_error: None,
+            total: subtotal,
+        }
+    }
+
+    // lines that can be bought as they are
+    pub(crate) fn purchasable_lines(&self) -> Vec<&CartSummaryLine> {
+        self.lines.iter()
+            .filter(|line| !self.problems.iter().any(|problem| problem.product_id() == line.product_id))
+            .collect()
+    }
+
+    pub(crate) fn apply_promotion(
+        &mut self,
+        promotion: Result<Promotion, PromotionError>,
+    ) {
+        let applied = promotion.and_then(|promotion| promotion.apply(&self.purchasable_lines(), &self.subtotal));
+
+        match applied {
+            Ok(applied) => {
+                self.total = self.subtotal.clone() - applied.discount.clone();
+                self.promotion = Some(applied);
+                self.promotion_error = None;
+            }
+            Err(error) => {
+                self.total = self.subtotal.clone();
+                self.promotion = None;
+                self.promotion_error = Some(error);
+            }
+        }
+    }
 }
\ No newline at end of file
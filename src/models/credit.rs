@@ -0,0 +1,120 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{credit_ledger, gift_cards};
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = gift_cards)]
+pub(crate) struct GiftCard {
+    pub(crate) id: String,
+    pub(crate) code: String,
+    pub(crate) initial_balance: BigDecimal,
+    pub(crate) balance: BigDecimal,
+    pub(crate) issued_by: String,
+    pub(crate) note: Option<String>,
+    pub(crate) expires_at: Option<NaiveDateTime>,
+    pub(crate) redeemed_by: Option<String>,
+    pub(crate) redeemed_at: Option<NaiveDateTime>,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+// a code is generated by the database when none is given
+#[derive(Debug, Insertable)]
+#[diesel(table_name = gift_cards)]
+pub(crate) struct NewGiftCard {
+    pub(crate) code: Option<String>,
+    pub(crate) initial_balance: BigDecimal,
+    pub(crate) balance: BigDecimal,
+    pub(crate) issued_by: String,
+    pub(crate) note: Option<String>,
+    pub(crate) expires_at: Option<NaiveDateTime>,
+}
+
+// one movement of a user's store credit, credits are positive and spending is negative
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = credit_ledger)]
+pub(crate) struct CreditEntry {
+    pub(crate) id: String,
+    pub(crate) user_id: String,
+    pub(crate) amount: BigDecimal,
+    pub(crate) reason: String,
+    pub(crate) gift_card_id: Option<String>,
+    pub(crate) order_id: Option<String>,
+    pub(crate) checkout_session_id: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Default, Insertable)]
+#[diesel(table_name = credit_ledger)]
+pub(crate) struct NewCreditEntry {
+    pub(crate) user_id: String,
+    pub(crate) amount: BigDecimal,
+    pub(crate) reason: String,
+    pub(crate) gift_card_id: Option<String>,
+    pub(crate) order_id: Option<String>,
+    pub(crate) checkout_session_id: Option<String>,
+    pub(crate) note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CreditBalance {
+    pub(crate) balance: BigDecimal,
+    pub(crate) entries: Vec<CreditEntry>,
+}
+
+impl CreditBalance {
+    pub(crate) fn new(entries: Vec<CreditEntry>) -> Self {
+        let balance = entries.iter().map(|entry| entry.amount.clone()).sum::<BigDecimal>();
+
+        Self {
+            balance: balance.with_scale(2),
+            entries,
+        }
+    }
+}
+
+// why a gift card can not be redeemed
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum GiftCardError {
+    NotFound { code: String },
+    AlreadyRedeemed { code: String },
+    Expired { code: String, expires_at: NaiveDateTime },
+}
+
+// why an order can not be refunded as store credit
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum CreditRefundError {
+    NotRefundable { status: String },
+    AmountTooHigh { amount: BigDecimal, refundable: BigDecimal },
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GiftCardIssue {
+    pub(crate) amount: BigDecimal,
+    pub(crate) code: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GiftCardRedeem {
+    pub(crate) code: String,
+}
+
+// credit given to a user by an admin, as a goodwill grant or as the refund of an order
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreditGrant {
+    pub(crate) amount: BigDecimal,
+    pub(crate) note: Option<String>,
+}
+
+// sent with the checkout to pay with the user's store credit
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreditQuery {
+    pub(crate) use_credit: Option<bool>,
+}
\ No newline at end of file
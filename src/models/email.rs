@@ -0,0 +1,32 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::{Queryable, Insertable};
+use serde::Serialize;
+
+use crate::schema::email_outbox;
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = email_outbox)]
+pub(crate) struct OutboxEmail {
+    pub(crate) id: String,
+    pub(crate) order_id: Option<String>,
+    pub(crate) kind: String,
+    pub(crate) recipient: String,
+    pub(crate) subject: String,
+    pub(crate) body: String,
+    pub(crate) status: String,
+    pub(crate) attempts: i32,
+    pub(crate) last_error: Option<String>,
+    pub(crate) next_attempt_at: NaiveDateTime,
+    pub(crate) sent_at: Option<NaiveDateTime>,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Default, Insertable)]
+#[diesel(table_name = email_outbox)]
+pub(crate) struct NewOutboxEmail {
+    pub(crate) order_id: Option<String>,
+    pub(crate) kind: Option<String>,
+    pub(crate) recipient: Option<String>,
+    pub(crate) subject: Option<String>,
+    pub(crate) body: Option<String>,
+}
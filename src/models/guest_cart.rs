@@ -0,0 +1,41 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::{Associations, Identifiable, Insertable, Queryable};
+use serde::Serialize;
+
+use crate::models::product::Product;
+use crate::schema::{guest_cart_items, guest_carts};
+
+#[derive(Debug, Clone, Serialize, Identifiable, Queryable)]
+#[diesel(table_name = guest_carts)]
+pub(crate) struct GuestCart {
+    pub(crate) id: String,
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Associations, Identifiable, Queryable, Insertable)]
+#[diesel(primary_key(guest_cart_id, product_id))]
+#[diesel(belongs_to(GuestCart))]
+#[diesel(belongs_to(Product))]
+#[diesel(table_name = guest_cart_items)]
+pub(crate) struct GuestCartItem {
+    pub(crate) guest_cart_id: String,
+    pub(crate) product_id: String,
+    pub(crate) quantity: i32,
+}
+
+// returned when a guest cart is created, the token has to be sent back on every guest cart request
+#[derive(Debug, Serialize)]
+pub(crate) struct GuestCartCreated {
+    pub(crate) token: String,
+    pub(crate) expires_at: NaiveDateTime,
+}
+
+// how long a guest cart and its token live
+pub(crate) fn guest_cart_ttl() -> chrono::Duration {
+    let hours = std::env::var("GUEST_CART_TTL_HOURS")
+        .map(|s| s.parse::<i64>().expect("GUEST_CART_TTL_HOURS should be a number"))
+        .unwrap_or(72);
+
+    chrono::Duration::hours(hours)
+}
\ No newline at end of file
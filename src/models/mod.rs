@@ -2,4 +2,15 @@ pub mod dbpool;
 pub mod product;
 pub mod user;
 pub mod cart;
-pub mod order;
\ No newline at end of file
+pub mod order;
+pub mod email;
+pub mod guest_cart;
+pub mod abandoned_cart;
+pub mod promotion;
+pub mod credit;
+pub mod shipping;
+pub mod address;
+pub mod shipment;
+pub mod order_return;
+pub mod pagination;
+pub mod api_key;
\ No newline at end of file
@@ -1,9 +1,12 @@
+use std::{collections::HashMap, str::FromStr};
+
+use bigdecimal::{BigDecimal, RoundingMode};
 use diesel::{prelude::{Queryable, Insertable}, AsChangeset};
 use serde::{Serialize, Deserialize};
 
 use crate::schema::orders;
 
-use super::product::Product;
+use super::{address::ShippingAddress, product::Product, shipment::{quantities, Shipment}};
 
 
 #[derive(Debug, Clone, Serialize, Queryable, Insertable, AsChangeset)]
@@ -14,9 +17,27 @@ pub(crate) struct Order {
     pub(crate) products: serde_json::Value,
     pub(crate) status: String,
     pub(crate) name: String,
-    pub(crate) address: String,
     pub(crate) created_at: chrono::NaiveDateTime,
     pub(crate) updated_at: chrono::NaiveDateTime,
+    pub(crate) promotion_code: Option<String>,
+    pub(crate) discount: Option<BigDecimal>,
+    pub(crate) store_credit: Option<BigDecimal>,
+    pub(crate) shipping_method_id: Option<String>,
+    pub(crate) shipping_method: Option<String>,
+    pub(crate) shipping_cost: Option<BigDecimal>,
+    pub(crate) phone: Option<String>,
+    pub(crate) address_line1: Option<String>,
+    pub(crate) address_line2: Option<String>,
+    pub(crate) address_city: Option<String>,
+    pub(crate) address_state: Option<String>,
+    pub(crate) address_postal_code: Option<String>,
+    pub(crate) address_country: Option<String>,
+    pub(crate) payment_intent_id: Option<String>,
+    pub(crate) refunded_amount: BigDecimal,
+    pub(crate) canceled_by: Option<String>,
+    pub(crate) canceled_at: Option<chrono::NaiveDateTime>,
+    pub(crate) prices: serde_json::Value,
+    pub(crate) amount_paid: BigDecimal,
 }
 
 #[derive(Debug, Default, Deserialize, Queryable, Insertable, AsChangeset)]
@@ -27,9 +48,27 @@ pub(crate) struct NewOrder {
     pub(crate) products: Option<serde_json::Value>,
     pub(crate) status: Option<String>,
     pub(crate) name: Option<String>,
-    pub(crate) address: Option<String>,
     pub(crate) created_at: Option<chrono::NaiveDateTime>,
     pub(crate) updated_at: Option<chrono::NaiveDateTime>,
+    pub(crate) promotion_code: Option<String>,
+    pub(crate) discount: Option<BigDecimal>,
+    pub(crate) store_credit: Option<BigDecimal>,
+    pub(crate) shipping_method_id: Option<String>,
+    pub(crate) shipping_method: Option<String>,
+    pub(crate) shipping_cost: Option<BigDecimal>,
+    pub(crate) phone: Option<String>,
+    pub(crate) address_line1: Option<String>,
+    pub(crate) address_line2: Option<String>,
+    pub(crate) address_city: Option<String>,
+    pub(crate) address_state: Option<String>,
+    pub(crate) address_postal_code: Option<String>,
+    pub(crate) address_country: Option<String>,
+    pub(crate) payment_intent_id: Option<String>,
+    pub(crate) refunded_amount: Option<BigDecimal>,
+    pub(crate) canceled_by: Option<String>,
+    pub(crate) canceled_at: Option<chrono::NaiveDateTime>,
+    pub(crate) prices: Option<serde_json::Value>,
+    pub(crate) amount_paid: Option<BigDecimal>,
 }
 
 #[derive(Debug, Serialize)]
@@ -39,33 +78,129 @@ pub(crate) struct ExpandedOrder {
     pub(crate) products: Vec<OrderProduct>,
     pub(crate) status: String,
     pub(crate) name: String,
-    pub(crate) address: String,
+    pub(crate) address: ShippingAddress,
     pub(crate) created_at: chrono::NaiveDateTime,
     pub(crate) updated_at: chrono::NaiveDateTime,
+    pub(crate) promotion_code: Option<String>,
+    pub(crate) discount: Option<BigDecimal>,
+    pub(crate) store_credit: Option<BigDecimal>,
+    pub(crate) shipping_method_id: Option<String>,
+    pub(crate) shipping_method: Option<String>,
+    pub(crate) shipping_cost: Option<BigDecimal>,
+    pub(crate) amount_paid: BigDecimal,
+    pub(crate) refunded_amount: BigDecimal,
+    pub(crate) canceled_by: Option<String>,
+    pub(crate) canceled_at: Option<chrono::NaiveDateTime>,
+    pub(crate) shipments: Vec<Shipment>,
 }
 
 impl ExpandedOrder{
     pub(crate) fn new(
         order: Order,  
         products: Vec<OrderProduct>,   
+        shipments: Vec<Shipment>,
     ) -> Self {
+        let address = order.address();
+
         Self {
             id: order.id,
             user_id: order.user_id,
             products: products,
             status: order.status,
             name: order.name,
-            address: order.address,
+            address,
             created_at: order.created_at,
             updated_at: order.updated_at,
+            promotion_code: order.promotion_code,
+            discount: order.discount,
+            store_credit: order.store_credit,
+            shipping_method_id: order.shipping_method_id,
+            shipping_method: order.shipping_method,
+            shipping_cost: order.shipping_cost,
+            amount_paid: order.amount_paid,
+            refunded_amount: order.refunded_amount,
+            canceled_by: order.canceled_by,
+            canceled_at: order.canceled_at,
+            shipments,
+        }
+    }
+}
+
+impl Order {
+    pub(crate) fn address(&self) -> ShippingAddress {
+        ShippingAddress {
+            name: self.name.clone(),
+            phone: self.phone.clone(),
+            line1: self.address_line1.clone(),
+            line2: self.address_line2.clone(),
+            city: self.address_city.clone(),
+            state: self.address_state.clone(),
+            postal_code: self.address_postal_code.clone(),
+            country: self.address_country.clone(),
         }
     }
+
+    // what the lines cost at the prices the order was placed with
+    pub(crate) fn lines_subtotal(&self, lines: &HashMap<String, i32>) -> BigDecimal {
+        lines.iter()
+            .map(|(product_id, quantity)| {
+                let price = self.prices.get(product_id)
+                    .and_then(|price| price.as_str())
+                    .and_then(|price| BigDecimal::from_str(price).ok())
+                    .unwrap_or_default();
+                price * BigDecimal::from(*quantity)
+            })
+            .sum()
+    }
+
+    // the share of the payment that went to these lines, the discount and store credit are spread over every line
+    pub(crate) fn paid_for(&self, lines: &HashMap<String, i32>) -> BigDecimal {
+        let subtotal = self.lines_subtotal(&quantities(&self.products));
+        if subtotal <= BigDecimal::from(0) {
+            return BigDecimal::from(0);
+        }
+
+        let paid_for_products = (self.amount_paid.clone() - self.shipping_cost.clone().unwrap_or_default()).max(BigDecimal::from(0));
+        (self.lines_subtotal(lines) * paid_for_products / subtotal).with_scale_round(2, RoundingMode::Down)
+    }
+
+    // what can still be paid back, to the card or as store credit
+    pub(crate) fn refundable(&self) -> BigDecimal {
+        (self.amount_paid.clone() + self.store_credit.clone().unwrap_or_default() - self.refunded_amount.clone())
+            .max(BigDecimal::from(0))
+    }
+}
+
+impl NewOrder {
+    // the changes that set the recipient and shipping address of an order
+    pub(crate) fn from_address(
+        address: ShippingAddress,
+    ) -> Self {
+        Self {
+            name: Some(address.name),
+            phone: address.phone,
+            address_line1: address.line1,
+            address_line2: address.line2,
+            address_city: address.city,
+            address_state: address.state,
+            address_postal_code: address.postal_code,
+            address_country: address.country,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum CancelError {
+    NotCancelable { status: String },
+    NoPayment,
 }
 
 #[derive(Debug, Serialize)]
 pub(crate) struct OrderProduct {
-    product: Product,
-    quantity: i32,
+    pub(crate) product: Product,
+    pub(crate) quantity: i32,
 }
 
 impl OrderProduct {
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::returns;
+
+use super::shipment::quantities;
+
+// goods a customer sends back from an order, moving from requested to approved, received, refunding and refunded
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = returns)]
+pub(crate) struct OrderReturn {
+    pub(crate) id: String,
+    pub(crate) order_id: String,
+    pub(crate) user_id: String,
+    pub(crate) products: serde_json::Value,
+    pub(crate) reason: Option<String>,
+    pub(crate) status: String,
+    pub(crate) note: Option<String>,
+    pub(crate) refund_amount: Option<BigDecimal>,
+    pub(crate) stripe_refund_id: Option<String>,
+    pub(crate) approved_at: Option<NaiveDateTime>,
+    pub(crate) received_at: Option<NaiveDateTime>,
+    pub(crate) refunded_at: Option<NaiveDateTime>,
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = returns)]
+pub(crate) struct NewOrderReturn {
+    pub(crate) order_id: String,
+    pub(crate) user_id: String,
+    pub(crate) products: serde_json::Value,
+    pub(crate) reason: Option<String>,
+}
+
+impl OrderReturn {
+    pub(crate) fn lines(&self) -> HashMap<String, i32> {
+        quantities(&self.products)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ReturnRequest {
+    pub(crate) products: HashMap<String, i32>,
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ReturnDecision {
+    pub(crate) note: Option<String>,
+}
+
+// without an amount the returned lines are refunded for what was paid for them
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ReturnRefund {
+    pub(crate) amount: Option<BigDecimal>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ReturnQuery {
+    pub(crate) status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum ReturnError {
+    NotShipped { status: String },
+    NothingToReturn,
+    NotInOrder { product_id: String },
+    InvalidQuantity { product_id: String, quantity: i32 },
+    TooMany { product_id: String, returnable: i32 },
+    WrongStatus { status: String, expected: &'static str },
+    NoPayment,
+    InvalidAmount { amount: BigDecimal },
+    AmountTooHigh { amount: BigDecimal, refundable: BigDecimal },
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+// `?page=` counts from 1, `?per_page=` is capped so a client cannot ask for everything at once
+#[derive(Debug, Deserialize)]
+pub(crate) struct PageQuery {
+    pub(crate) page: Option<i64>,
+    pub(crate) per_page: Option<i64>,
+}
+
+impl PageQuery {
+    pub(crate) fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub(crate) fn per_page(&self) -> i64 {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE)
+    }
+
+    pub(crate) fn offset(&self) -> i64 {
+        (self.page() - 1) * self.per_page()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Page<T> {
+    pub(crate) items: Vec<T>,
+    pub(crate) page: i64,
+    pub(crate) per_page: i64,
+    pub(crate) total: i64,
+}
+
+impl<T> Page<T> {
+    pub(crate) fn new(items: Vec<T>, query: &PageQuery, total: i64) -> Self {
+        Self {
+            items,
+            page: query.page(),
+            per_page: query.per_page(),
+            total,
+        }
+    }
+}
\ No newline at end of file
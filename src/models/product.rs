@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
 use diesel::AsChangeset;
@@ -21,6 +23,12 @@ pub(crate) struct Product {
     pub(crate) price_id: Option<String>,
     pub(crate) active: bool,
     pub(crate) variant_id: i32,
+    pub(crate) weight: Option<BigDecimal>,
+    pub(crate) length: Option<BigDecimal>,
+    pub(crate) width: Option<BigDecimal>,
+    pub(crate) height: Option<BigDecimal>,
+    pub(crate) weight_unit: String,
+    pub(crate) dimension_unit: String,
 }
 
 impl Product {
@@ -51,8 +59,26 @@ impl Product {
                 Some(metadata) => Some(metadata.get("variant_id").map(|s| s.parse::<i32>().unwrap()).unwrap_or(0)),
                 None => None,
             }.unwrap_or(0),
+            weight: metadata_decimal(&stripe_product.metadata, "weight"),
+            length: metadata_decimal(&stripe_product.metadata, "length"),
+            width: metadata_decimal(&stripe_product.metadata, "width"),
+            height: metadata_decimal(&stripe_product.metadata, "height"),
+            weight_unit: metadata_unit(&stripe_product.metadata, "weight_unit", &WEIGHT_UNITS).unwrap_or("kg".to_string()),
+            dimension_unit: metadata_unit(&stripe_product.metadata, "dimension_unit", &DIMENSION_UNITS).unwrap_or("cm".to_string()),
         }
     }
+
+    // the weight converted to kilograms, shipping rates are per kilogram
+    pub(crate) fn weight_kg(&self) -> Option<BigDecimal> {
+        let kilograms_per_unit = match self.weight_unit.as_str() {
+            "g" => "0.001",
+            "lb" => "0.45359237",
+            "oz" => "0.028349523125",
+            _ => "1",
+        };
+
+        self.weight.clone().map(|weight| weight * BigDecimal::from_str(kilograms_per_unit).unwrap())
+    }
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Insertable, AsChangeset)]
@@ -70,6 +96,12 @@ pub(crate) struct NewProduct {
     pub(crate) price_id: Option<String>,
     pub(crate) active: Option<bool>,
     pub(crate) variant_id: Option<i32>,
+    pub(crate) weight: Option<BigDecimal>,
+    pub(crate) length: Option<BigDecimal>,
+    pub(crate) width: Option<BigDecimal>,
+    pub(crate) height: Option<BigDecimal>,
+    pub(crate) weight_unit: Option<String>,
+    pub(crate) dimension_unit: Option<String>,
 }
 
 impl NewProduct {
@@ -106,10 +138,51 @@ impl NewProduct {
                 },
                 None => None,
             },
+            weight: metadata_decimal(&stripe_product.metadata, "weight"),
+            length: metadata_decimal(&stripe_product.metadata, "length"),
+            width: metadata_decimal(&stripe_product.metadata, "width"),
+            height: metadata_decimal(&stripe_product.metadata, "height"),
+            weight_unit: metadata_unit(&stripe_product.metadata, "weight_unit", &WEIGHT_UNITS),
+            dimension_unit: metadata_unit(&stripe_product.metadata, "dimension_unit", &DIMENSION_UNITS),
         }
     }
 }
 
+fn metadata_string(
+    metadata: &Option<stripe::Metadata>,
+    key: &str,
+) -> Option<String> {
+    metadata.as_ref().and_then(|metadata| metadata.get(key)).map(|s| s.to_string())
+}
+
+// values that do not parse are treated as missing rather than failing the webhook
+fn metadata_decimal(
+    metadata: &Option<stripe::Metadata>,
+    key: &str,
+) -> Option<BigDecimal> {
+    metadata_string(metadata, key).and_then(|s| BigDecimal::from_str(&s).ok())
+}
+
+// units typed into the stripe dashboard like `KG` or `lbs` are matched to the ones the database accepts, anything
+// else is treated as missing rather than failing the webhook on every retry
+fn metadata_unit(
+    metadata: &Option<stripe::Metadata>,
+    key: &str,
+    units: &[&str],
+) -> Option<String> {
+    let value = metadata_string(metadata, key)?;
+    let unit = value.trim().to_lowercase();
+
+    let known = [unit.as_str(), unit.trim_end_matches('s')].into_iter()
+        .find(|unit| units.contains(unit))
+        .map(String::from);
+    if known.is_none() {
+        log::warn!("ignoring unknown {} {:?} in the stripe metadata", key, value);
+    }
+
+    known
+}
+
 // used to get a list of ids from the client
 #[derive(Debug, Deserialize)]
 pub(crate) struct ProductIds {
@@ -125,6 +198,8 @@ pub(crate) struct NewProductPayload {
     pub(crate) category: String,
     pub(crate) price: BigDecimal,
     pub(crate) variant_id: i32,
+    #[serde(flatten)]
+    pub(crate) physical: PhysicalAttributes,
 }
 
 #[derive(Debug, Deserialize)]
@@ -134,4 +209,60 @@ pub(crate) struct UpdatePayload {
     pub(crate) description: String,
     pub(crate) images: Vec<String>,
     pub(crate) is_active: bool,
+    #[serde(flatten)]
+    pub(crate) physical: PhysicalAttributes,
+}
+
+pub(crate) const WEIGHT_UNITS: [&str; 4] = ["kg", "g", "lb", "oz"];
+pub(crate) const DIMENSION_UNITS: [&str; 2] = ["cm", "in"];
+
+// weight and package dimensions sent by the admin client, stored in the stripe metadata
+#[derive(Debug, Default, Deserialize, Clone)]
+pub(crate) struct PhysicalAttributes {
+    pub(crate) weight: Option<BigDecimal>,
+    pub(crate) length: Option<BigDecimal>,
+    pub(crate) width: Option<BigDecimal>,
+    pub(crate) height: Option<BigDecimal>,
+    pub(crate) weight_unit: Option<String>,
+    pub(crate) dimension_unit: Option<String>,
+}
+
+impl PhysicalAttributes {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let negative = [&self.weight, &self.length, &self.width, &self.height].into_iter()
+            .flatten()
+            .any(|value| value < &BigDecimal::from(0));
+        if negative {
+            return Err("weight and dimensions can not be negative".to_string());
+        }
+
+        if let Some(weight_unit) = &self.weight_unit {
+            if !WEIGHT_UNITS.contains(&weight_unit.as_str()) {
+                return Err(format!("weight_unit must be one of {}", WEIGHT_UNITS.join(", ")));
+            }
+        }
+
+        if let Some(dimension_unit) = &self.dimension_unit {
+            if !DIMENSION_UNITS.contains(&dimension_unit.as_str()) {
+                return Err(format!("dimension_unit must be one of {}", DIMENSION_UNITS.join(", ")));
+            }
+        }
+
+        Ok(())
+    }
+
+    // only the attributes that were sent, so an update leaves the others as they are
+    pub(crate) fn metadata(&self) -> Vec<(String, String)> {
+        [
+            ("weight", self.weight.as_ref().map(|weight| weight.to_string())),
+            ("length", self.length.as_ref().map(|length| length.to_string())),
+            ("width", self.width.as_ref().map(|width| width.to_string())),
+            ("height", self.height.as_ref().map(|height| height.to_string())),
+            ("weight_unit", self.weight_unit.clone()),
+            ("dimension_unit", self.dimension_unit.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value?)))
+        .collect()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,141 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::{prelude::{Insertable, Queryable}, AsChangeset};
+use serde::{Deserialize, Serialize};
+
+use crate::models::cart::CartSummaryLine;
+use crate::schema::promotions;
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = promotions)]
+pub(crate) struct Promotion {
+    pub(crate) id: String,
+    pub(crate) code: String,
+    pub(crate) description: Option<String>,
+    pub(crate) kind: String,
+    pub(crate) amount: BigDecimal,
+    pub(crate) usage_limit: Option<i32>,
+    pub(crate) times_redeemed: i32,
+    pub(crate) starts_at: Option<NaiveDateTime>,
+    pub(crate) ends_at: Option<NaiveDateTime>,
+    pub(crate) min_subtotal: Option<BigDecimal>,
+    pub(crate) categories: Option<Vec<Option<String>>>,
+    pub(crate) product_ids: Option<Vec<Option<String>>>,
+    pub(crate) active: bool,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Insertable, AsChangeset)]
+#[diesel(table_name = promotions)]
+pub(crate) struct NewPromotion {
+    pub(crate) code: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) kind: Option<String>,
+    pub(crate) amount: Option<BigDecimal>,
+    pub(crate) usage_limit: Option<i32>,
+    pub(crate) starts_at: Option<NaiveDateTime>,
+    pub(crate) ends_at: Option<NaiveDateTime>,
+    pub(crate) min_subtotal: Option<BigDecimal>,
+    pub(crate) categories: Option<Vec<Option<String>>>,
+    pub(crate) product_ids: Option<Vec<Option<String>>>,
+    pub(crate) active: Option<bool>,
+}
+
+// why a code can not be used on a cart
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum PromotionError {
+    NotFound { code: String },
+    Inactive { code: String },
+    NotStarted { code: String, starts_at: NaiveDateTime },
+    Expired { code: String, ends_at: NaiveDateTime },
+    UsageLimitReached { code: String },
+    BelowMinSubtotal { code: String, min_subtotal: BigDecimal },
+    NoEligibleItems { code: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct AppliedPromotion {
+    pub(crate) promotion_id: String,
+    pub(crate) code: String,
+    pub(crate) discount: BigDecimal,
+}
+
+impl Promotion {
+    // work out the discount this promotion gives on the purchasable lines of a cart
+    pub(crate) fn apply(
+        &self,
+        lines: &[&CartSummaryLine],
+        subtotal: &BigDecimal,
+    ) -> Result<AppliedPromotion, PromotionError> {
+        let code = self.code.clone();
+        let current_time = chrono::Local::now().naive_local();
+
+        if !self.active {
+            return Err(PromotionError::Inactive { code });
+        }
+
+        if let Some(starts_at) = self.starts_at {
+            if current_time < starts_at {
+                return Err(PromotionError::NotStarted { code, starts_at });
+            }
+        }
+
+        if let Some(ends_at) = self.ends_at {
+            if current_time > ends_at {
+                return Err(PromotionError::Expired { code, ends_at });
+            }
+        }
+
+        if let Some(usage_limit) = self.usage_limit {
+            if self.times_redeemed >= usage_limit {
+                return Err(PromotionError::UsageLimitReached { code });
+            }
+        }
+
+        if let Some(min_subtotal) = &self.min_subtotal {
+            if subtotal < min_subtotal {
+                return Err(PromotionError::BelowMinSubtotal { code, min_subtotal: min_subtotal.clone() });
+            }
+        }
+
+        // only lines matching the product or category scope are discounted, no scope means the whole cart
+        let eligible_subtotal: BigDecimal = lines.iter()
+            .filter(|line| self.applies_to(line))
+            .map(|line| line.line_total.clone())
+            .sum();
+
+        if eligible_subtotal == BigDecimal::from(0) {
+            return Err(PromotionError::NoEligibleItems { code });
+        }
+
+        let discount = match self.kind.as_str() {
+            "percent" => (eligible_subtotal * self.amount.clone() / BigDecimal::from(100)).round(2),
+            _ => self.amount.clone().min(eligible_subtotal),
+        };
+
+        Ok(AppliedPromotion {
+            promotion_id: self.id.clone(),
+            code,
+            discount: discount.with_scale(2),
+        })
+    }
+
+    fn applies_to(&self, line: &CartSummaryLine) -> bool {
+        let product_ids = self.product_ids.clone().unwrap_or_default().into_iter().flatten().collect::<Vec<String>>();
+        let categories = self.categories.clone().unwrap_or_default().into_iter().flatten().collect::<Vec<String>>();
+
+        if product_ids.is_empty() && categories.is_empty() {
+            return true;
+        }
+
+        product_ids.contains(&line.product_id)
+            || line.category.as_ref().map(|category| categories.contains(category)).unwrap_or(false)
+    }
+}
+
+// the optional discount code sent with the cart summary and checkout
+#[derive(Debug, Deserialize)]
+pub(crate) struct PromotionQuery {
+    pub(crate) code: Option<String>,
+}
\ No newline at end of file
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::shipments;
+
+// one package sent for an order, an order can go out in several of them
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = shipments)]
+pub(crate) struct Shipment {
+    pub(crate) id: String,
+    pub(crate) order_id: String,
+    pub(crate) products: serde_json::Value,
+    pub(crate) carrier: String,
+    pub(crate) tracking_number: Option<String>,
+    pub(crate) tracking_url: Option<String>,
+    pub(crate) shipped_at: NaiveDateTime,
+    pub(crate) created_by: String,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = shipments)]
+pub(crate) struct NewShipment {
+    pub(crate) order_id: String,
+    pub(crate) products: serde_json::Value,
+    pub(crate) carrier: String,
+    pub(crate) tracking_number: Option<String>,
+    pub(crate) tracking_url: Option<String>,
+    pub(crate) shipped_at: Option<NaiveDateTime>,
+    pub(crate) created_by: String,
+}
+
+// leaving the products out ships everything that has not been shipped yet
+#[derive(Debug, Deserialize)]
+pub(crate) struct ShipmentPayload {
+    pub(crate) carrier: String,
+    pub(crate) tracking_number: Option<String>,
+    pub(crate) tracking_url: Option<String>,
+    pub(crate) shipped_at: Option<NaiveDateTime>,
+    pub(crate) products: Option<HashMap<String, i32>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum ShipmentError {
+    MissingCarrier,
+    OrderClosed { status: String },
+    NothingToShip,
+    NotInOrder { product_id: String },
+    InvalidQuantity { product_id: String, quantity: i32 },
+    TooMany { product_id: String, remaining: i32 },
+}
+
+impl Shipment {
+    pub(crate) fn lines(&self) -> HashMap<String, i32> {
+        quantities(&self.products)
+    }
+}
+
+impl ShipmentPayload {
+    // the tracking page of the well known carriers is filled in when no url is given
+    pub(crate) fn tracking_url(&self) -> Option<String> {
+        if let Some(tracking_url) = &self.tracking_url {
+            return Some(tracking_url.trim().to_string()).filter(|url| !url.is_empty());
+        }
+
+        let tracking_number = self.tracking_number.as_ref()?.trim();
+        let base = match self.carrier.trim().to_lowercase().as_str() {
+            "ups" => "https://www.ups.com/track?tracknum=",
+            "usps" => "https://tools.usps.com/go/TrackConfirmAction?tLabels=",
+            "fedex" => "https://www.fedex.com/fedextrack/?trknbr=",
+            "dhl" => "https://www.dhl.com/en/express/tracking.html?AWB=",
+            _ => return None,
+        };
+
+        Some(format!("{}{}", base, tracking_number))
+    }
+}
+
+// read a `{product_id: quantity}` object like the products of orders and shipments
+pub(crate) fn quantities(products: &serde_json::Value) -> HashMap<String, i32> {
+    products.as_object()
+        .map(|products| products.iter()
+            .map(|(product_id, quantity)| (product_id.clone(), quantity.as_i64().unwrap_or(0) as i32))
+            .collect())
+        .unwrap_or_default()
+}
+
+// what is left of each product of an order after the shipments that already went out
+pub(crate) fn unshipped(order_products: &serde_json::Value, shipments: &[Shipment]) -> HashMap<String, i32> {
+    let mut remaining = quantities(order_products);
+    for shipment in shipments {
+        for (product_id, quantity) in shipment.lines() {
+            if let Some(left) = remaining.get_mut(&product_id) {
+                *left -= quantity;
+            }
+        }
+    }
+
+    remaining.retain(|_, left| *left > 0);
+    remaining
+}
\ No newline at end of file
@@ -0,0 +1,180 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::{prelude::{Insertable, Queryable}, AsChangeset};
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{shipping_methods, shipping_zones};
+
+// stripe takes at most five shipping options on a checkout session, a zone can't offer more than that
+pub(crate) const MAX_ACTIVE_METHODS: i64 = 5;
+
+// a group of destination countries sharing the same shipping methods
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = shipping_zones)]
+pub(crate) struct ShippingZone {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) countries: Vec<Option<String>>,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Insertable, AsChangeset)]
+#[diesel(table_name = shipping_zones)]
+pub(crate) struct NewShippingZone {
+    pub(crate) name: Option<String>,
+    pub(crate) countries: Option<Vec<Option<String>>>,
+}
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = shipping_methods)]
+pub(crate) struct ShippingMethod {
+    pub(crate) id: String,
+    pub(crate) zone_id: String,
+    pub(crate) name: String,
+    pub(crate) kind: String,
+    pub(crate) amount: BigDecimal,
+    pub(crate) rate_per_kg: Option<BigDecimal>,
+    pub(crate) free_over: Option<BigDecimal>,
+    pub(crate) min_days: Option<i32>,
+    pub(crate) max_days: Option<i32>,
+    pub(crate) active: bool,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Insertable, AsChangeset)]
+#[diesel(table_name = shipping_methods)]
+pub(crate) struct NewShippingMethod {
+    pub(crate) zone_id: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) kind: Option<String>,
+    pub(crate) amount: Option<BigDecimal>,
+    pub(crate) rate_per_kg: Option<BigDecimal>,
+    pub(crate) free_over: Option<BigDecimal>,
+    pub(crate) min_days: Option<i32>,
+    pub(crate) max_days: Option<i32>,
+    pub(crate) active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ShippingZoneDetails {
+    #[serde(flatten)]
+    pub(crate) zone: ShippingZone,
+    pub(crate) methods: Vec<ShippingMethod>,
+}
+
+// what a shipping method costs for a given cart
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ShippingQuote {
+    pub(crate) method_id: String,
+    pub(crate) name: String,
+    pub(crate) cost: BigDecimal,
+    pub(crate) min_days: Option<i32>,
+    pub(crate) max_days: Option<i32>,
+}
+
+impl ShippingMethod {
+    pub(crate) fn quote(
+        &self,
+        subtotal: &BigDecimal,
+        weight_kg: &BigDecimal,
+    ) -> ShippingQuote {
+        let cost = match self.kind.as_str() {
+            // every started kilogram is charged
+            "weight" => {
+                let kilograms = weight_kg.with_scale_round(0, bigdecimal::RoundingMode::Ceiling);
+                self.amount.clone() + self.rate_per_kg.clone().unwrap_or_default() * kilograms
+            },
+            "free_over" if self.free_over.as_ref().map(|free_over| subtotal >= free_over).unwrap_or(false) => BigDecimal::from(0),
+            _ => self.amount.clone(),
+        };
+
+        ShippingQuote {
+            method_id: self.id.clone(),
+            name: self.name.clone(),
+            cost: cost.with_scale(2),
+            min_days: self.min_days,
+            max_days: self.max_days,
+        }
+    }
+}
+
+// the zone a cart ships to can not be read from the address collected by stripe, so it is picked up front
+#[derive(Debug, Deserialize)]
+pub(crate) struct ShippingQuery {
+    pub(crate) country: Option<String>,
+}
+
+impl ShippingQuery {
+    // two letter country code, the store ships to the US unless told otherwise
+    pub(crate) fn country(&self) -> String {
+        self.country.clone().map(|country| country.trim().to_uppercase()).unwrap_or("US".to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum ShippingError {
+    NotShipped { country: String },
+    NoMethods { country: String },
+    TooManyMethods { zone_id: String, max: i64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::ShippingMethod;
+
+    fn method(kind: &str, amount: &str, rate_per_kg: Option<&str>, free_over: Option<&str>) -> ShippingMethod {
+        ShippingMethod {
+            id: "shipmethod-test".to_string(),
+            zone_id: "shipzone-test".to_string(),
+            name: "Standard".to_string(),
+            kind: kind.to_string(),
+            amount: BigDecimal::from_str(amount).unwrap(),
+            rate_per_kg: rate_per_kg.map(|rate| BigDecimal::from_str(rate).unwrap()),
+            free_over: free_over.map(|free_over| BigDecimal::from_str(free_over).unwrap()),
+            min_days: Some(2),
+            max_days: Some(5),
+            active: true,
+            created_at: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    fn cost(method: &ShippingMethod, subtotal: &str, weight_kg: &str) -> String {
+        method.quote(&BigDecimal::from_str(subtotal).unwrap(), &BigDecimal::from_str(weight_kg).unwrap()).cost.to_string()
+    }
+
+    #[test]
+    fn flat_costs_the_amount() {
+        let flat = method("flat", "4.5", None, None);
+
+        assert_eq!(cost(&flat, "0", "0"), "4.50");
+        assert_eq!(cost(&flat, "250.00", "12.3"), "4.50");
+
+        let quote = flat.quote(&BigDecimal::from(10), &BigDecimal::from(1));
+        assert_eq!((quote.method_id.as_str(), quote.name.as_str()), ("shipmethod-test", "Standard"));
+        assert_eq!((quote.min_days, quote.max_days), (Some(2), Some(5)));
+    }
+
+    #[test]
+    fn weight_charges_every_started_kilogram() {
+        let weight = method("weight", "3.00", Some("1.25"), None);
+
+        assert_eq!(cost(&weight, "10.00", "0"), "3.00");
+        assert_eq!(cost(&weight, "10.00", "0.2"), "4.25");
+        assert_eq!(cost(&weight, "10.00", "1"), "4.25");
+        assert_eq!(cost(&weight, "10.00", "2.01"), "6.75");
+    }
+
+    #[test]
+    fn free_over_is_free_from_the_threshold() {
+        let free_over = method("free_over", "6.00", None, Some("50.00"));
+
+        assert_eq!(cost(&free_over, "49.99", "1"), "6.00");
+        assert_eq!(cost(&free_over, "50.00", "1"), "0.00");
+        assert_eq!(cost(&free_over, "120.00", "1"), "0.00");
+    }
+}
\ No newline at end of file
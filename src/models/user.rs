@@ -1,25 +1,141 @@
-use diesel::prelude::{Insertable, Queryable, Identifiable};
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use diesel::{prelude::{Queryable, Identifiable}, AsChangeset};
 use serde::{Serialize, Deserialize};
 
-use crate::schema::users;
+use crate::{models::{address::{is_phone_number, UserAddress}, cart::CartItem, credit::CreditEntry, order::ExpandedOrder, order_return::OrderReturn}, schema::users};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Identifiable, Queryable, Insertable)]
+#[derive(Debug, Clone, Serialize, Deserialize, Identifiable, Queryable)]
 #[diesel(table_name = users)]
 pub(crate) struct User {
     pub(crate) id: String,
     pub(crate) email: String,
     pub(crate) roles: Option<Vec<Option<String>>>,
     pub(crate) stripe_id: Option<String>,
+    pub(crate) display_name: Option<String>,
+    pub(crate) phone: Option<String>,
+    pub(crate) marketing_opt_in: bool,
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) last_seen_at: Option<NaiveDateTime>,
+    pub(crate) erased_at: Option<NaiveDateTime>,
+    pub(crate) disabled_at: Option<NaiveDateTime>,
+    pub(crate) disabled_reason: Option<String>,
 }
 
+/// the fields a user may change on their own profile, anything else like the roles is refused
 #[derive(Debug, Clone, Deserialize)]
-pub(crate) struct SubmitRoles {
+#[serde(deny_unknown_fields)]
+pub(crate) struct ProfileUpdate {
+    pub(crate) display_name: Option<String>,
+    pub(crate) phone: Option<String>,
+    pub(crate) marketing_opt_in: Option<bool>,
+}
+
+// fields left out stay as they are, a blank display name or phone clears it
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = users)]
+pub(crate) struct ProfileChanges {
+    pub(crate) display_name: Option<Option<String>>,
+    pub(crate) phone: Option<Option<String>>,
+    pub(crate) marketing_opt_in: Option<bool>,
+}
+
+// a field of a profile update that was refused
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ProfileError {
+    pub(crate) field: &'static str,
+    pub(crate) reason: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ProfileValidationErrors {
+    pub(crate) errors: Vec<ProfileError>,
+}
+
+impl ProfileUpdate {
+    pub(crate) fn into_changes(self) -> Result<ProfileChanges, ProfileValidationErrors> {
+        let clean = |value: Option<String>| value.map(|value| Some(value.trim().to_string()).filter(|value| !value.is_empty()));
+        let changes = ProfileChanges {
+            display_name: clean(self.display_name),
+            phone: clean(self.phone),
+            marketing_opt_in: self.marketing_opt_in,
+        };
+
+        let mut errors = Vec::new();
+
+        if let Some(Some(display_name)) = &changes.display_name {
+            if display_name.chars().count() > 100 {
+                errors.push(ProfileError { field: "display_name", reason: "must be at most 100 characters" });
+            }
+        }
+        if let Some(Some(phone)) = &changes.phone {
+            if !is_phone_number(phone) {
+                errors.push(ProfileError { field: "phone", reason: "must be a phone number of 7 to 15 digits" });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(changes)
+        } else {
+            Err(ProfileValidationErrors { errors })
+        }
+    }
+}
+
+/// sent by the auth0 action whenever a user signs up or logs in, the roles are only used for a new user
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ProvisionUser {
     pub(crate) user_id: String,
-    pub(crate) stripe_customer_id: String,
-    pub(crate) roles: Vec<String>,
+    pub(crate) email: String,
+    pub(crate) roles: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub(crate) struct UserId {
     pub(crate) id: String,
+}
+
+/// everything stored about a user, returned by the data export
+#[derive(Debug, Serialize)]
+pub(crate) struct UserExport {
+    pub(crate) profile: User,
+    pub(crate) addresses: Vec<UserAddress>,
+    pub(crate) cart: Vec<CartItem>,
+    pub(crate) orders: Vec<ExpandedOrder>,
+    pub(crate) returns: Vec<OrderReturn>,
+    pub(crate) store_credit: Vec<CreditEntry>,
+    pub(crate) exported_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum ErasureError {
+    // the address is still needed to deliver these
+    OpenOrders { orders: Vec<String> },
+}
+
+// `?q=` matches part of the email or the id
+#[derive(Debug, Deserialize)]
+pub(crate) struct UserSearchQuery {
+    pub(crate) q: Option<String>,
+}
+
+/// a user as staff see it, spend is what their orders were paid with the card and store credit less refunds
+#[derive(Debug, Serialize)]
+pub(crate) struct UserOverview {
+    #[serde(flatten)]
+    pub(crate) user: User,
+    pub(crate) order_count: i64,
+    pub(crate) lifetime_spend: BigDecimal,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct DisableUser {
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum RoleError {
+    UnknownRole { role: String },
 }
\ No newline at end of file
@@ -2,24 +2,61 @@ use actix_web::web::{self};
 
 use crate::{
     handlers::{
-        carts::{add_to_cart, get_cart_items, update_cart, update_cart_item},
+        abandoned_carts::{
+            get_abandoned_cart_report, restore_abandoned_cart, send_abandoned_cart_recovery,
+        },
+        api_keys::{admin_create_api_key, admin_get_api_keys, admin_revoke_api_key},
+        addresses::{
+            create_user_address, delete_user_address, get_user_address, get_user_addresses,
+            update_user_address,
+        },
+        carts::{
+            add_to_cart, admin_get_cart_items, admin_update_cart, get_cart_items, get_cart_summary,
+            update_cart, update_cart_item,
+        },
         checkout::{cancel_checkout, checkout},
+        credit::{
+            get_credit, get_gift_cards, grant_credit, issue_gift_card, redeem_gift_card,
+            refund_order_to_credit,
+        },
+        guest_carts::{
+            add_to_guest_cart, create_guest_cart, get_guest_cart_items, merge_guest_cart,
+            update_guest_cart,
+        },
         orders::{
-            create_order_handler, delete_order, get_expanded_orders,
-            get_expanded_orders_by_user_id, get_order_by_id, get_orders, update_order,
-            update_order_status,
+            cancel_order, create_order_handler, create_shipment, delete_order, get_expanded_orders,
+            get_expanded_orders_by_user_id, get_my_order, get_my_orders, get_order_by_id,
+            get_order_shipments, get_orders, update_order, update_order_address, update_order_status,
         },
         products::{
             create_product, delete_product, get_active_products, get_active_products_by_category,
             get_all_categories, get_all_products, get_multiple_products_by_id, get_product_by_id,
             get_product_by_name, get_products_by_category, update_product,
         },
-        users::{create_user, delete_user, get_user, index, update_user},
+        promotions::{create_promotion, delete_promotion, get_all_promotions, update_promotion},
+        returns::{
+            approve_return, get_my_returns, get_return, get_returns, receive_return,
+            refund_return, reject_return, request_return,
+        },
+        shipping::{
+            create_shipping_method, create_shipping_zone, delete_shipping_method,
+            delete_shipping_zone, get_shipping_rates, get_shipping_zones, update_shipping_method,
+            update_shipping_zone,
+        },
+        users::{
+            admin_disable_user, admin_enable_user, admin_get_user, admin_grant_role,
+            admin_revoke_role, admin_search_users, delete_user, erase_me, export_me, get_me,
+            get_user, index, provision_user, update_me,
+        },
     },
+    extractors::permissions::ProductsWrite,
+    middleware::permission_guard::PermissionGuard,
     stripe::webhook::webhook_handler,
 };
 
 pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
+    // staff routes carry their permission guard on the handler, they are registered after the customer
+    // routes next to them so those are matched first
     cfg.route("/", web::get().to(|| async { "Hello, world!" }))
         .service(
             web::scope("/api")
@@ -45,25 +82,86 @@ pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
                         .service(create_product)
                         .service(delete_product),
                 )
+                .service(
+                    // promotions
+                    web::scope("/promotion")
+                        .wrap(PermissionGuard::<ProductsWrite>::new())
+                        .service(get_all_promotions)
+                        .service(create_promotion)
+                        .service(update_promotion)
+                        .service(delete_promotion),
+                )
+                .service(
+                    // shipping
+                    web::scope("/shipping")
+                        .service(get_shipping_rates)
+                        .service(get_shipping_zones)
+                        .service(create_shipping_zone)
+                        .service(update_shipping_zone)
+                        .service(delete_shipping_zone)
+                        .service(create_shipping_method)
+                        .service(update_shipping_method)
+                        .service(delete_shipping_method),
+                )
                 .service(
                     // users
                     web::scope("/user")
-                        .service(create_user)
-                        .service(update_user)
-                        .service(delete_user)
+                        .service(provision_user)
+                        .service(get_credit)
+                        .service(redeem_gift_card)
+                        .service(get_user_addresses)
+                        .service(get_user_address)
+                        .service(create_user_address)
+                        .service(update_user_address)
+                        .service(delete_user_address)
                         .service(get_user)
-                        .service(index),
+                        .service(get_me)
+                        .service(update_me)
+                        .service(export_me)
+                        .service(erase_me)
+                        .service(index)
+                        .service(delete_user)
+                        .service(admin_search_users)
+                        .service(admin_get_api_keys)
+                        .service(admin_create_api_key)
+                        .service(admin_revoke_api_key)
+                        .service(admin_get_user)
+                        .service(admin_grant_role)
+                        .service(admin_revoke_role)
+                        .service(admin_disable_user)
+                        .service(admin_enable_user)
+                        .service(get_gift_cards)
+                        .service(issue_gift_card)
+                        .service(grant_credit),
                 )
                 .service(
                     // carts
                     web::scope("/cart")
                         .service(get_cart_items)
+                        .service(get_cart_summary)
                         .service(add_to_cart)
                         .service(update_cart_item)
-                        .service(update_cart),
+                        .service(update_cart)
+                        .service(create_guest_cart)
+                        .service(get_guest_cart_items)
+                        .service(add_to_guest_cart)
+                        .service(update_guest_cart)
+                        .service(merge_guest_cart)
+                        .service(restore_abandoned_cart)
+                        .service(admin_get_cart_items)
+                        .service(admin_update_cart)
+                        .service(get_abandoned_cart_report)
+                        .service(send_abandoned_cart_recovery),
                 )
                 .service(
+                    // orders
                     web::scope("/order")
+                        .service(get_my_orders)
+                        .service(get_my_order)
+                        .service(cancel_order)
+                        .service(request_return)
+                        .service(get_my_returns)
+                        .service(get_return)
                         .service(get_orders)
                         .service(get_order_by_id)
                         .service(get_expanded_orders)
@@ -71,7 +169,16 @@ pub(crate) fn routes(cfg: &mut web::ServiceConfig) {
                         .service(create_order_handler)
                         .service(update_order)
                         .service(update_order_status)
+                        .service(update_order_address)
+                        .service(create_shipment)
+                        .service(get_order_shipments)
+                        .service(get_returns)
+                        .service(approve_return)
+                        .service(reject_return)
+                        .service(receive_return)
+                        .service(refund_return)
+                        .service(refund_order_to_credit)
                         .service(delete_order),
                 ),
         );
-}
+}
\ No newline at end of file
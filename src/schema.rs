@@ -1,10 +1,103 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    abandoned_carts (id) {
+        id -> Varchar,
+        user_id -> Varchar,
+        products -> Jsonb,
+        item_count -> Int4,
+        value -> Numeric,
+        last_activity_at -> Timestamp,
+        detected_at -> Timestamp,
+        recovery_sent_at -> Nullable<Timestamp>,
+        recovered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Varchar,
+        name -> Varchar,
+        prefix -> Varchar,
+        key_hash -> Varchar,
+        scopes -> Array<Nullable<Text>>,
+        created_by -> Varchar,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     carts (user_id, product_id) {
         user_id -> Varchar,
         product_id -> Varchar,
         quantity -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    credit_ledger (id) {
+        id -> Varchar,
+        user_id -> Varchar,
+        amount -> Numeric,
+        reason -> Varchar,
+        gift_card_id -> Nullable<Varchar>,
+        order_id -> Nullable<Varchar>,
+        checkout_session_id -> Nullable<Varchar>,
+        note -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    email_outbox (id) {
+        id -> Varchar,
+        order_id -> Nullable<Varchar>,
+        kind -> Varchar,
+        recipient -> Varchar,
+        subject -> Varchar,
+        body -> Text,
+        status -> Varchar,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        next_attempt_at -> Timestamp,
+        sent_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    gift_cards (id) {
+        id -> Varchar,
+        code -> Varchar,
+        initial_balance -> Numeric,
+        balance -> Numeric,
+        issued_by -> Varchar,
+        note -> Nullable<Varchar>,
+        expires_at -> Nullable<Timestamp>,
+        redeemed_by -> Nullable<Varchar>,
+        redeemed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    guest_cart_items (guest_cart_id, product_id) {
+        guest_cart_id -> Varchar,
+        product_id -> Varchar,
+        quantity -> Int4,
+    }
+}
+
+diesel::table! {
+    guest_carts (id) {
+        id -> Varchar,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
     }
 }
 
@@ -15,9 +108,27 @@ diesel::table! {
         products -> Jsonb,
         status -> Varchar,
         name -> Varchar,
-        address -> Varchar,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        promotion_code -> Nullable<Varchar>,
+        discount -> Nullable<Numeric>,
+        store_credit -> Nullable<Numeric>,
+        shipping_method_id -> Nullable<Varchar>,
+        shipping_method -> Nullable<Varchar>,
+        shipping_cost -> Nullable<Numeric>,
+        phone -> Nullable<Varchar>,
+        address_line1 -> Nullable<Varchar>,
+        address_line2 -> Nullable<Varchar>,
+        address_city -> Nullable<Varchar>,
+        address_state -> Nullable<Varchar>,
+        address_postal_code -> Nullable<Varchar>,
+        address_country -> Nullable<Varchar>,
+        payment_intent_id -> Nullable<Varchar>,
+        refunded_amount -> Numeric,
+        canceled_by -> Nullable<Varchar>,
+        canceled_at -> Nullable<Timestamp>,
+        prices -> Jsonb,
+        amount_paid -> Numeric,
     }
 }
 
@@ -35,6 +146,109 @@ diesel::table! {
         price_id -> Nullable<Varchar>,
         active -> Bool,
         variant_id -> Int4,
+        weight -> Nullable<Numeric>,
+        length -> Nullable<Numeric>,
+        width -> Nullable<Numeric>,
+        height -> Nullable<Numeric>,
+        weight_unit -> Varchar,
+        dimension_unit -> Varchar,
+    }
+}
+
+diesel::table! {
+    promotions (id) {
+        id -> Varchar,
+        code -> Varchar,
+        description -> Nullable<Varchar>,
+        kind -> Varchar,
+        amount -> Numeric,
+        usage_limit -> Nullable<Int4>,
+        times_redeemed -> Int4,
+        starts_at -> Nullable<Timestamp>,
+        ends_at -> Nullable<Timestamp>,
+        min_subtotal -> Nullable<Numeric>,
+        categories -> Nullable<Array<Nullable<Text>>>,
+        product_ids -> Nullable<Array<Nullable<Text>>>,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    returns (id) {
+        id -> Varchar,
+        order_id -> Varchar,
+        user_id -> Varchar,
+        products -> Jsonb,
+        reason -> Nullable<Varchar>,
+        status -> Varchar,
+        note -> Nullable<Varchar>,
+        refund_amount -> Nullable<Numeric>,
+        stripe_refund_id -> Nullable<Varchar>,
+        approved_at -> Nullable<Timestamp>,
+        received_at -> Nullable<Timestamp>,
+        refunded_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    shipments (id) {
+        id -> Varchar,
+        order_id -> Varchar,
+        products -> Jsonb,
+        carrier -> Varchar,
+        tracking_number -> Nullable<Varchar>,
+        tracking_url -> Nullable<Varchar>,
+        shipped_at -> Timestamp,
+        created_by -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    shipping_methods (id) {
+        id -> Varchar,
+        zone_id -> Varchar,
+        name -> Varchar,
+        kind -> Varchar,
+        amount -> Numeric,
+        rate_per_kg -> Nullable<Numeric>,
+        free_over -> Nullable<Numeric>,
+        min_days -> Nullable<Int4>,
+        max_days -> Nullable<Int4>,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    shipping_zones (id) {
+        id -> Varchar,
+        name -> Varchar,
+        countries -> Array<Nullable<Text>>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_addresses (id) {
+        id -> Varchar,
+        user_id -> Varchar,
+        label -> Nullable<Varchar>,
+        name -> Varchar,
+        phone -> Nullable<Varchar>,
+        line1 -> Varchar,
+        line2 -> Nullable<Varchar>,
+        city -> Varchar,
+        state -> Nullable<Varchar>,
+        postal_code -> Varchar,
+        country -> Varchar,
+        is_default_shipping -> Bool,
+        is_default_billing -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -44,16 +258,51 @@ diesel::table! {
         email -> Varchar,
         roles -> Nullable<Array<Nullable<Text>>>,
         stripe_id -> Nullable<Varchar>,
+        display_name -> Nullable<Varchar>,
+        phone -> Nullable<Varchar>,
+        marketing_opt_in -> Bool,
+        created_at -> Timestamp,
+        last_seen_at -> Nullable<Timestamp>,
+        erased_at -> Nullable<Timestamp>,
+        disabled_at -> Nullable<Timestamp>,
+        disabled_reason -> Nullable<Varchar>,
     }
 }
 
+diesel::joinable!(abandoned_carts -> users (user_id));
 diesel::joinable!(carts -> products (product_id));
 diesel::joinable!(carts -> users (user_id));
+diesel::joinable!(credit_ledger -> gift_cards (gift_card_id));
+diesel::joinable!(credit_ledger -> orders (order_id));
+diesel::joinable!(credit_ledger -> users (user_id));
+diesel::joinable!(email_outbox -> orders (order_id));
+diesel::joinable!(gift_cards -> users (redeemed_by));
+diesel::joinable!(guest_cart_items -> guest_carts (guest_cart_id));
+diesel::joinable!(guest_cart_items -> products (product_id));
+diesel::joinable!(orders -> shipping_methods (shipping_method_id));
 diesel::joinable!(orders -> users (user_id));
+diesel::joinable!(returns -> orders (order_id));
+diesel::joinable!(returns -> users (user_id));
+diesel::joinable!(shipments -> orders (order_id));
+diesel::joinable!(shipping_methods -> shipping_zones (zone_id));
+diesel::joinable!(user_addresses -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    abandoned_carts,
+    api_keys,
     carts,
+    credit_ledger,
+    email_outbox,
+    gift_cards,
+    guest_cart_items,
+    guest_carts,
     orders,
     products,
+    promotions,
+    returns,
+    shipments,
+    shipping_methods,
+    shipping_zones,
+    user_addresses,
     users,
 );
@@ -2,7 +2,7 @@ use actix_cors::Cors;
 use actix_web::{App, HttpServer, middleware::Logger, web};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
-use crate::{routes::routes, database::init_db::initialize_db_pool};
+use crate::{routes::routes, extractors::verifier::Authenticator, handlers::users::ProvisioningSecret, database::init_db::initialize_db_pool, jobs::abandoned_carts::run_abandoned_cart_job, mailer::{outbox::run_outbox_worker, transport::Mailer}};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
@@ -19,6 +19,14 @@ pub(crate) async fn server() -> std::io::Result<()> {
 
     let stripe_client = stripe::Client::new(std::env::var("STRIPE_SECRET_KEY").expect("STRIPE_SECRET_KEY should be set"));
 
+    let authenticator = web::Data::new(Authenticator::from_env());
+    let provisioning_secret = web::Data::new(ProvisioningSecret::from_env());
+
+    // deliver queued transactional emails in the background
+    actix_web::rt::spawn(run_outbox_worker(pool.clone(), Mailer::from_env()));
+    // look for carts that were left behind
+    actix_web::rt::spawn(run_abandoned_cart_job(pool.clone()));
+
     HttpServer::new(move || {
         App::new()
             // CORS
@@ -35,6 +43,10 @@ pub(crate) async fn server() -> std::io::Result<()> {
             .app_data(web::Data::new(pool.clone()))
             // pass the stripe client to application so we can access it inside handlers
             .app_data(web::Data::new(stripe_client.clone()))
+            // pass the token verifier to the claims extractor
+            .app_data(authenticator.clone())
+            // pass the secret provisioning requests are signed with
+            .app_data(provisioning_secret.clone())
             .configure(routes)
     })
     .bind(("0.0.0.0", 8080))?
@@ -3,7 +3,7 @@ use std::borrow::Borrow;
 use actix_web::{post, HttpRequest, web, HttpResponse, Responder, Result};
 use stripe::{Webhook, EventType, EventObject, Client};
 
-use crate::{models::dbpool::PgPool, handlers::{products::{wh_create_product, wh_change_price, wh_update_product, wh_delete_product}, checkout::{checkout_success, checkout_expired}}};
+use crate::{models::dbpool::PgPool, handlers::{products::{wh_create_product, wh_change_price, wh_update_product, wh_delete_product}, checkout::{checkout_success, checkout_expired}, returns::charge_refunded, users::{wh_update_customer, wh_delete_customer}}};
 
 #[post("stripe_webhooks")]
 pub async fn webhook_handler(
@@ -59,6 +59,21 @@ pub async fn handle_webhook(
                     checkout_expired(pool, session).await?;
                 }
             }
+            EventType::ChargeRefunded => {
+                if let EventObject::Charge(charge) = event.data.object {
+                    charge_refunded(pool, charge).await?;
+                }
+            }
+            EventType::CustomerUpdated => {
+                if let EventObject::Customer(customer) = event.data.object {
+                    wh_update_customer(pool, customer).await?;
+                }
+            }
+            EventType::CustomerDeleted => {
+                if let EventObject::Customer(customer) = event.data.object {
+                    wh_delete_customer(pool, customer).await?;
+                }
+            }
             _ => {
                 log::info!("Unknown event encountered in webhook: {:?}", event.type_);
             }
@@ -0,0 +1,122 @@
+mod helpers;
+
+#[cfg(test)]
+mod api_key_tests {
+    use reqwest::{blocking::Client, Method, StatusCode};
+    use serde_json::{json, Value};
+
+    use crate::helpers::token::{get_test_admin_token, mint_token, SERVER_URL, TEST_USER_ID};
+
+    fn create_key(scopes: &[&str]) -> (String, String) {
+        let created = Client::new()
+            .post(format!("{}/api/user/admin/api_keys", SERVER_URL))
+            .header("Authorization", format!("Bearer {}", get_test_admin_token()))
+            .json(&json!({ "name": "integration test", "scopes": scopes }))
+            .send()
+            .unwrap()
+            .json::<Value>()
+            .unwrap();
+
+        (created["id"].as_str().unwrap().to_string(), created["key"].as_str().unwrap().to_string())
+    }
+
+    fn status(method: Method, path: &str, key: &str) -> StatusCode {
+        Client::new()
+            .request(method, format!("{}{}", SERVER_URL, path))
+            .header("X-Api-Key", key)
+            .send()
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn key_grants_its_scopes() {
+        let (_, key) = create_key(&["orders:read"]);
+
+        assert_eq!(status(Method::GET, "/api/order", &key), StatusCode::OK);
+        assert_eq!(status(Method::POST, "/api/product/create", &key), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn revoked_key_is_refused() {
+        let (id, key) = create_key(&["orders:read"]);
+
+        let revoked = Client::new()
+            .delete(format!("{}/api/user/admin/api_keys/{}", SERVER_URL, id))
+            .header("Authorization", format!("Bearer {}", get_test_admin_token()))
+            .send()
+            .unwrap();
+        assert_eq!(revoked.status(), StatusCode::OK);
+
+        assert_eq!(status(Method::GET, "/api/order", &key), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn unknown_key_is_refused() {
+        assert_eq!(status(Method::GET, "/api/order", "evk_not-a-key"), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn unknown_scope_is_rejected() {
+        let response = Client::new()
+            .post(format!("{}/api/user/admin/api_keys", SERVER_URL))
+            .header("Authorization", format!("Bearer {}", get_test_admin_token()))
+            .json(&json!({ "name": "integration test", "scopes": ["everything"] }))
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn key_cannot_create_keys() {
+        let (_, key) = create_key(&["users:manage"]);
+
+        let response = Client::new()
+            .post(format!("{}/api/user/admin/api_keys", SERVER_URL))
+            .header("X-Api-Key", &key)
+            .json(&json!({ "name": "integration test", "scopes": ["users:manage"] }))
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    fn as_admin(method: Method, path: &str) -> StatusCode {
+        Client::new()
+            .request(method, format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", get_test_admin_token()))
+            .json(&json!({}))
+            .send()
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn key_stops_working_with_its_creator() {
+        let admin_role = format!("/api/user/admin/{}/roles/admin", TEST_USER_ID);
+        assert_eq!(as_admin(Method::PUT, &admin_role), StatusCode::OK);
+
+        let created = Client::new()
+            .post(format!("{}/api/user/admin/api_keys", SERVER_URL))
+            .header("Authorization", format!("Bearer {}", mint_token(TEST_USER_ID, &[])))
+            .json(&json!({ "name": "integration test", "scopes": ["orders:read"] }))
+            .send()
+            .unwrap()
+            .json::<Value>()
+            .unwrap();
+        let key = created["key"].as_str().unwrap().to_string();
+        assert_eq!(status(Method::GET, "/api/order", &key), StatusCode::OK);
+
+        // the creator no longer holds the scope
+        assert_eq!(as_admin(Method::DELETE, &admin_role), StatusCode::OK);
+        assert_eq!(status(Method::GET, "/api/order", &key), StatusCode::FORBIDDEN);
+
+        // the creator's account is disabled
+        assert_eq!(as_admin(Method::PUT, &admin_role), StatusCode::OK);
+        assert_eq!(as_admin(Method::POST, &format!("/api/user/admin/{}/disable", TEST_USER_ID)), StatusCode::OK);
+        assert_eq!(status(Method::GET, "/api/order", &key), StatusCode::FORBIDDEN);
+
+        assert_eq!(as_admin(Method::POST, &format!("/api/user/admin/{}/enable", TEST_USER_ID)), StatusCode::OK);
+        assert_eq!(as_admin(Method::DELETE, &admin_role), StatusCode::OK);
+    }
+}
\ No newline at end of file
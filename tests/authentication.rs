@@ -0,0 +1,62 @@
+mod helpers;
+
+#[cfg(test)]
+mod authentication_tests {
+    use reqwest::{blocking::Client, StatusCode};
+
+    use crate::helpers::token::{sign, sign_with, test_claims, SERVER_URL, TEST_USER_ID};
+
+    fn status(token: &str) -> StatusCode {
+        Client::new()
+            .get(format!("{}/api/user/index", SERVER_URL))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn valid_token() {
+        assert_eq!(status(&sign(&test_claims(TEST_USER_ID, &[]))), StatusCode::OK);
+    }
+
+    #[test]
+    fn wrong_secret() {
+        assert_eq!(status(&sign_with(&test_claims(TEST_USER_ID, &[]), "not-the-secret")), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn wrong_audience() {
+        let mut claims = test_claims(TEST_USER_ID, &[]);
+        claims["aud"] = "another-api".into();
+        assert_eq!(status(&sign(&claims)), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn wrong_issuer() {
+        let mut claims = test_claims(TEST_USER_ID, &[]);
+        claims["iss"] = "https://someone-else.example/".into();
+        assert_eq!(status(&sign(&claims)), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn expired_within_leeway() {
+        let mut claims = test_claims(TEST_USER_ID, &[]);
+        claims["exp"] = (jsonwebtoken::get_current_timestamp() - 10).into();
+        assert_eq!(status(&sign(&claims)), StatusCode::OK);
+    }
+
+    #[test]
+    fn expired() {
+        let mut claims = test_claims(TEST_USER_ID, &[]);
+        claims["exp"] = (jsonwebtoken::get_current_timestamp() - 3600).into();
+        assert_eq!(status(&sign(&claims)), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn missing_audience() {
+        let mut claims = test_claims(TEST_USER_ID, &[]);
+        claims.as_object_mut().unwrap().remove("aud");
+        assert_eq!(status(&sign(&claims)), StatusCode::UNAUTHORIZED);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,150 @@
+mod helpers;
+
+#[cfg(test)]
+mod authorization_tests {
+    use reqwest::{blocking::Client, Method, StatusCode};
+
+    use crate::helpers::token::{get_test_admin_token, get_test_user_token, SERVER_URL};
+
+    fn status(method: Method, path: &str, token: Option<&str>) -> StatusCode {
+        let mut request = Client::new().request(method, format!("{}{}", SERVER_URL, path));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+
+        request.send().unwrap().status()
+    }
+
+    // no token is a 401, a token without the admin role a 403, and an admin gets through to the handler
+    fn assert_admin_only(method: Method, path: &str) {
+        assert_eq!(status(method.clone(), path, None), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(method.clone(), path, Some("not-a-token")), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(method.clone(), path, Some(&get_test_user_token())), StatusCode::FORBIDDEN);
+
+        let admin_status = status(method, path, Some(&get_test_admin_token()));
+        assert_ne!(admin_status, StatusCode::UNAUTHORIZED);
+        assert_ne!(admin_status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn get_orders() {
+        assert_admin_only(Method::GET, "/api/order");
+    }
+
+    #[test]
+    fn get_order_by_id() {
+        assert_admin_only(Method::GET, "/api/order/id/order-does-not-exist");
+    }
+
+    #[test]
+    fn get_expanded_orders() {
+        assert_admin_only(Method::GET, "/api/order/expand");
+    }
+
+    #[test]
+    fn get_expanded_orders_by_user_id() {
+        assert_admin_only(Method::GET, "/api/order/expand/user/auth0|65e645fbdebe6c8392fbe22b");
+    }
+
+    #[test]
+    fn create_order() {
+        // without a body an admin is turned away by the json extractor instead
+        assert_admin_only(Method::POST, "/api/order/create");
+    }
+
+    #[test]
+    fn update_order() {
+        assert_admin_only(Method::POST, "/api/order/update/order-does-not-exist");
+    }
+
+    #[test]
+    fn update_order_status() {
+        assert_admin_only(Method::POST, "/api/order/update/order-does-not-exist/status");
+    }
+
+    #[test]
+    fn delete_order() {
+        assert_admin_only(Method::POST, "/api/order/delete/order-does-not-exist");
+    }
+
+    #[test]
+    fn delete_user() {
+        assert_admin_only(Method::DELETE, "/api/user/delete");
+    }
+
+    #[test]
+    fn admin_search_users() {
+        assert_admin_only(Method::GET, "/api/user/admin?q=example");
+    }
+
+    #[test]
+    fn admin_get_user() {
+        assert_admin_only(Method::GET, "/api/user/admin/user-does-not-exist");
+    }
+
+    #[test]
+    fn admin_grant_role() {
+        assert_admin_only(Method::PUT, "/api/user/admin/user-does-not-exist/roles/fulfillment");
+    }
+
+    #[test]
+    fn admin_revoke_role() {
+        assert_admin_only(Method::DELETE, "/api/user/admin/user-does-not-exist/roles/fulfillment");
+    }
+
+    #[test]
+    fn admin_disable_user() {
+        assert_admin_only(Method::POST, "/api/user/admin/user-does-not-exist/disable");
+    }
+
+    #[test]
+    fn admin_enable_user() {
+        assert_admin_only(Method::POST, "/api/user/admin/user-does-not-exist/enable");
+    }
+
+    #[test]
+    fn admin_get_api_keys() {
+        assert_admin_only(Method::GET, "/api/user/admin/api_keys");
+    }
+
+    #[test]
+    fn admin_create_api_key() {
+        assert_admin_only(Method::POST, "/api/user/admin/api_keys");
+    }
+
+    #[test]
+    fn admin_revoke_api_key() {
+        assert_admin_only(Method::DELETE, "/api/user/admin/api_keys/apikey-does-not-exist");
+    }
+
+    #[test]
+    fn create_product() {
+        assert_admin_only(Method::POST, "/api/product/create");
+    }
+
+    #[test]
+    fn customer_routes_stay_open() {
+        let token = get_test_user_token();
+        assert_eq!(status(Method::GET, "/api/product", None), StatusCode::OK);
+        assert_eq!(status(Method::GET, "/api/user/index", Some(&token)), StatusCode::OK);
+        assert_eq!(status(Method::GET, "/api/order/returns/mine", Some(&token)), StatusCode::OK);
+    }
+
+    #[test]
+    fn unknown_routes_are_not_found() {
+        for path in ["/api/order/nope", "/api/user/nope", "/api/cart/nope", "/api/shipping/nope", "/api/product/nope/nope"] {
+            assert_eq!(status(Method::GET, path, None), StatusCode::NOT_FOUND, "{}", path);
+            assert_eq!(status(Method::GET, path, Some(&get_test_user_token())), StatusCode::NOT_FOUND, "{}", path);
+        }
+    }
+
+    #[test]
+    fn shipping_zones() {
+        assert_admin_only(Method::GET, "/api/shipping/zones");
+    }
+
+    #[test]
+    fn admin_cart() {
+        assert_admin_only(Method::GET, "/api/cart/admin/auth0|65e645fbdebe6c8392fbe22b");
+    }
+}
\ No newline at end of file
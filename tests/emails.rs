@@ -0,0 +1,72 @@
+mod helpers;
+
+#[cfg(test)]
+mod email_tests {
+    use std::time::{Duration, Instant};
+
+    use reqwest::{blocking::Client, StatusCode};
+    use serde_json::{json, Value};
+
+    use crate::helpers::stripe::unique_id;
+    use crate::helpers::token::{get_test_admin_token, SERVER_URL, TEST_USER_ID};
+
+    // the server has to send through mailpit for these, see the README
+    fn mailpit_url() -> String {
+        std::env::var("MAILPIT_URL").unwrap_or("http://localhost:8025".to_string())
+    }
+
+    fn as_admin(path: &str, body: Value) -> StatusCode {
+        Client::new()
+            .post(format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", get_test_admin_token()))
+            .json(&body)
+            .send()
+            .unwrap()
+            .status()
+    }
+
+    // the message mailpit caught with this subject, waiting for the outbox worker to get to it
+    fn caught(subject: &str) -> Option<Value> {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(30) {
+            let messages = Client::new()
+                .get(format!("{}/api/v1/messages", mailpit_url()))
+                .send()
+                .unwrap()
+                .json::<Value>()
+                .unwrap();
+
+            let message = messages["messages"].as_array().unwrap().iter()
+                .find(|message| message["Subject"] == subject)
+                .cloned();
+            if message.is_some() {
+                return message;
+            }
+
+            std::thread::sleep(Duration::from_millis(500));
+        }
+
+        None
+    }
+
+    #[test]
+    fn status_change_is_emailed() {
+        let order_id = unique_id("order_emails");
+        let created = as_admin("/api/order/create", json!({
+            "id": order_id,
+            "user_id": TEST_USER_ID,
+            "products": {},
+            "status": "processing",
+            "name": "Email test",
+        }));
+        assert_eq!(created, StatusCode::OK);
+
+        let updated = as_admin(&format!("/api/order/update/{}/status", order_id), json!({ "status": "delievered" }));
+        assert_eq!(updated, StatusCode::OK);
+
+        let message = caught(&format!("Your order {} was delivered", order_id)).expect("the email should reach mailpit");
+        assert_eq!(message["To"].as_array().unwrap().len(), 1);
+
+        as_admin(&format!("/api/order/delete/{}", order_id), Value::Null);
+    }
+}
\ No newline at end of file
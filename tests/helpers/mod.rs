@@ -1 +1,3 @@
+pub mod provisioning;
+pub mod stripe;
 pub mod token;
\ No newline at end of file
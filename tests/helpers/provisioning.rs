@@ -0,0 +1,38 @@
+// every test binary that declares `mod helpers` compiles these, not all of them use every helper
+#![allow(dead_code)]
+
+use hmac::{Hmac, Mac};
+use reqwest::blocking::{Client, Response};
+use sha2::Sha256;
+
+use super::token::SERVER_URL;
+
+/// the hex HMAC-SHA256 of `{timestamp}.{body}` the provisioning endpoint checks
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// call the provisioning endpoint the way the auth0 action does
+pub fn provision(body: &str, timestamp: i64, secret: &str) -> Response {
+    Client::new()
+        .post(format!("{}/api/user/provision", SERVER_URL))
+        .header("Content-Type", "application/json")
+        .header("X-Provisioning-Timestamp", timestamp.to_string())
+        .header("X-Provisioning-Signature", signature(secret, timestamp, body))
+        .body(body.to_string())
+        .send()
+        .unwrap()
+}
+
+pub fn secret() -> String {
+    dotenv::dotenv().ok();
+    std::env::var("AUTH0_PROVISIONING_SECRET").unwrap()
+}
+
+/// provision a user signed with the configured secret
+pub fn provision_user(user_id: &str, email: &str) -> Response {
+    let body = serde_json::json!({ "user_id": user_id, "email": email, "roles": [] }).to_string();
+    provision(&body, chrono::Utc::now().timestamp(), &secret())
+}
\ No newline at end of file
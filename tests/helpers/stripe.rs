@@ -0,0 +1,95 @@
+// every test binary that declares `mod helpers` compiles these, not all of them use every helper
+#![allow(dead_code)]
+
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use super::token::SERVER_URL;
+
+// the signing secret the webhook handler checks stripe events against
+const WEBHOOK_SECRET: &str = "whsec_dead008dac3b2554665d4d4a7fef5edd47f2cd699e480867c80ef987a0d2d9ef";
+
+/// an id no other test run has used
+pub fn unique_id(prefix: &str) -> String {
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos();
+    format!("{}_{}", prefix, nanos)
+}
+
+/// deliver an event to the webhook endpoint, signed the way stripe signs it
+pub fn send_event(event_type: &str, object: Value) -> StatusCode {
+    let payload = json!({
+        "id": unique_id("evt"),
+        "object": "event",
+        "type": event_type,
+        "created": 1,
+        "livemode": false,
+        "pending_webhooks": 1,
+        "data": { "object": object },
+    }).to_string();
+
+    let timestamp = jsonwebtoken::get_current_timestamp();
+    let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET.as_bytes()).unwrap();
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    reqwest::blocking::Client::new()
+        .post(format!("{}/api/stripe_webhooks", SERVER_URL))
+        .header("Stripe-Signature", format!("t={},v1={}", timestamp, signature))
+        .header("Content-Type", "application/json")
+        .body(payload)
+        .send()
+        .unwrap()
+        .status()
+}
+
+/// a product as stripe describes it, with the inventory kept in its metadata
+pub fn product(id: &str, inventory: i32) -> Value {
+    json!({
+        "id": id,
+        "object": "product",
+        "name": "Test product",
+        "active": true,
+        "created": 1,
+        "images": [],
+        "livemode": false,
+        "metadata": { "inventory": inventory.to_string() },
+        "updated": 1,
+    })
+}
+
+/// a charge of 55.00 with one refund of `amount` cents made for a return
+pub fn refunded_charge(payment_intent_id: &str, return_id: &str, amount: i64) -> Value {
+    json!({
+        "id": "ch_test",
+        "object": "charge",
+        "amount": 5500,
+        "amount_captured": 5500,
+        "amount_refunded": amount,
+        "captured": true,
+        "created": 1,
+        "currency": "usd",
+        "disputed": false,
+        "livemode": false,
+        "paid": true,
+        "refunded": false,
+        "status": "succeeded",
+        "payment_intent": payment_intent_id,
+        "billing_details": {},
+        "metadata": {},
+        "refunds": {
+            "object": "list",
+            "data": [{
+                "id": "re_test",
+                "object": "refund",
+                "amount": amount,
+                "created": 1,
+                "currency": "usd",
+                "metadata": { "return_id": return_id },
+            }],
+            "has_more": false,
+            "url": "/v1/refunds",
+        },
+    })
+}
\ No newline at end of file
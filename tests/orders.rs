@@ -0,0 +1,186 @@
+mod helpers;
+
+#[cfg(test)]
+mod order_tests {
+    use reqwest::{blocking::Client, StatusCode};
+    use serde_json::{json, Value};
+
+    use crate::helpers::provisioning::provision_user;
+    use crate::helpers::stripe::unique_id;
+    use crate::helpers::token::{get_test_admin_token, get_test_user_token, mint_token, SERVER_URL, TEST_USER_ID};
+
+    fn post(path: &str, token: &str, body: Value) -> (StatusCode, Value) {
+        let response = Client::new()
+            .post(format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&body)
+            .send()
+            .unwrap();
+        let status = response.status();
+
+        (status, response.json::<Value>().unwrap_or(Value::Null))
+    }
+
+    fn get(path: &str, token: &str) -> Value {
+        Client::new()
+            .get(format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .unwrap()
+            .json::<Value>()
+            .unwrap()
+    }
+
+    fn get_status(path: &str, token: &str) -> StatusCode {
+        Client::new()
+            .get(format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .unwrap()
+            .status()
+    }
+
+    // an order of the test user, paid through the payment intent when one is given
+    fn create_order(status: &str, payment_intent_id: Option<&str>) -> String {
+        create_paid_order(status, payment_intent_id, "10.00", "0")
+    }
+
+    fn create_paid_order(status: &str, payment_intent_id: Option<&str>, amount_paid: &str, store_credit: &str) -> String {
+        create_user_order(TEST_USER_ID, status, payment_intent_id, amount_paid, store_credit)
+    }
+
+    fn create_user_order(user_id: &str, status: &str, payment_intent_id: Option<&str>, amount_paid: &str, store_credit: &str) -> String {
+        let order_id = unique_id("order_cancel");
+        let (response_status, _) = post("/api/order/create", &get_test_admin_token(), json!({
+            "id": order_id,
+            "user_id": user_id,
+            "products": {},
+            "status": status,
+            "name": "Cancel test",
+            "amount_paid": amount_paid,
+            "store_credit": store_credit,
+            "payment_intent_id": payment_intent_id,
+        }));
+        assert_eq!(response_status, StatusCode::OK);
+
+        order_id
+    }
+
+    fn delete_order(order_id: &str) {
+        post(&format!("/api/order/delete/{}", order_id), &get_test_admin_token(), Value::Null);
+    }
+
+    #[test]
+    fn only_processing_orders_are_canceled() {
+        for status in ["shipped", "canceled"] {
+            let order_id = create_order(status, Some(&unique_id("pi_cancel")));
+
+            let (response_status, error) = post(&format!("/api/order/{}/cancel", order_id), &get_test_user_token(), Value::Null);
+            assert_eq!(response_status, StatusCode::BAD_REQUEST);
+            assert_eq!(error["error"], "not_cancelable");
+            assert_eq!(error["status"], status);
+
+            let order = get(&format!("/api/order/id/{}", order_id), &get_test_admin_token());
+            assert_eq!(order["status"], status);
+
+            delete_order(&order_id);
+        }
+    }
+
+    #[test]
+    fn unpaid_order_is_not_canceled() {
+        let order_id = create_order("processing", None);
+
+        let (status, error) = post(&format!("/api/order/{}/cancel", order_id), &get_test_user_token(), Value::Null);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "no_payment");
+
+        let order = get(&format!("/api/order/id/{}", order_id), &get_test_admin_token());
+        assert_eq!(order["status"], "processing");
+
+        delete_order(&order_id);
+    }
+
+    #[test]
+    fn others_cannot_cancel_the_order() {
+        let order_id = create_order("processing", Some(&unique_id("pi_cancel")));
+
+        let (status, _) = post(&format!("/api/order/{}/cancel", order_id), &mint_token("auth0|someone-else", &[]), Value::Null);
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let order = get(&format!("/api/order/id/{}", order_id), &get_test_admin_token());
+        assert_eq!(order["status"], "processing");
+
+        delete_order(&order_id);
+    }
+
+    fn credit_balance() -> f64 {
+        get("/api/user/credit", &get_test_user_token())["balance"].as_str().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn cancel_pays_back_only_what_is_left_after_a_credit_refund() {
+        // 30.00 paid with the card and 20.00 with store credit, all of it already refunded as store credit
+        let order_id = create_paid_order("processing", Some(&unique_id("pi_cancel")), "30.00", "20.00");
+        let (status, _) = post(&format!("/api/order/refund/{}/credit", order_id), &get_test_admin_token(), json!({ "amount": "50.00" }));
+        assert_eq!(status, StatusCode::OK);
+        let balance = credit_balance();
+
+        // nothing is left for the card or the store credit, so stripe is not asked
+        let (status, order) = post(&format!("/api/order/{}/cancel", order_id), &get_test_user_token(), Value::Null);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(order["status"], "canceled");
+        assert_eq!(order["refunded_amount"], "50.00");
+        assert_eq!(credit_balance(), balance);
+
+        delete_order(&order_id);
+    }
+
+    #[test]
+    fn others_cannot_see_the_order() {
+        let order_id = create_order("processing", None);
+
+        assert_eq!(get_status(&format!("/api/order/mine/{}", order_id), &get_test_user_token()), StatusCode::OK);
+        assert_eq!(get_status(&format!("/api/order/mine/{}", order_id), &mint_token("auth0|someone-else", &[])), StatusCode::NOT_FOUND);
+
+        let orders = get("/api/order/mine", &mint_token("auth0|someone-else", &[]));
+        assert!(orders["items"].as_array().unwrap().iter().all(|order| order["id"] != order_id.as_str()));
+
+        delete_order(&order_id);
+    }
+
+    #[test]
+    fn own_orders_come_a_page_at_a_time() {
+        // a user of its own, so the totals don't depend on orders other tests leave behind
+        let user_id = unique_id("auth0|orders_page");
+        provision_user(&user_id, &format!("{}@example.com", unique_id("orders_page")));
+        let token = mint_token(&user_id, &[]);
+        let order_ids = (0..3)
+            .map(|_| create_user_order(&user_id, "processing", None, "10.00", "0"))
+            .collect::<Vec<String>>();
+
+        let ids = |page: &Value| page["items"].as_array().unwrap().iter()
+            .map(|order| order["id"].as_str().unwrap().to_string())
+            .collect::<Vec<String>>();
+
+        // newest first
+        let first = get("/api/order/mine?page=1&per_page=2", &token);
+        assert_eq!((first["page"].clone(), first["per_page"].clone(), first["total"].clone()), (json!(1), json!(2), json!(3)));
+        assert_eq!(ids(&first), [order_ids[2].clone(), order_ids[1].clone()]);
+
+        let second = get("/api/order/mine?page=2&per_page=2", &token);
+        assert_eq!((second["page"].clone(), second["total"].clone()), (json!(2), json!(3)));
+        assert_eq!(ids(&second), [order_ids[0].clone()]);
+
+        let past_the_end = get("/api/order/mine?page=3&per_page=2", &token);
+        assert_eq!(past_the_end["total"], 3);
+        assert_eq!(past_the_end["items"], json!([]));
+
+        // a page size that is too large is capped
+        assert_eq!(get("/api/order/mine?per_page=1000", &token)["per_page"], 100);
+
+        for order_id in order_ids {
+            delete_order(&order_id);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+mod helpers;
+
+#[cfg(test)]
+mod provisioning_tests {
+    use reqwest::{blocking::Client, StatusCode};
+
+    use crate::helpers::provisioning::{provision, secret};
+    use crate::helpers::token::SERVER_URL;
+
+    const BODY: &str = r#"{"user_id":"auth0|provisioning-test","email":"provisioning@example.com","roles":[]}"#;
+
+    #[test]
+    fn unsigned() {
+        let response = Client::new()
+            .post(format!("{}/api/user/provision", SERVER_URL))
+            .body(BODY)
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn wrong_secret() {
+        let response = provision(BODY, chrono::Utc::now().timestamp(), "not-the-secret");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn stale_timestamp() {
+        let response = provision(BODY, chrono::Utc::now().timestamp() - 3600, &secret());
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn repeated_provisioning_keeps_the_customer() {
+        let first = provision(BODY, chrono::Utc::now().timestamp(), &secret());
+        assert_eq!(first.status(), StatusCode::OK);
+        let first = first.json::<serde_json::Value>().unwrap();
+        assert!(first["stripe_id"].is_string());
+
+        let second = provision(BODY, chrono::Utc::now().timestamp(), &secret())
+            .json::<serde_json::Value>()
+            .unwrap();
+        assert_eq!(first, second);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,190 @@
+mod helpers;
+
+#[cfg(test)]
+mod return_tests {
+    use reqwest::{blocking::Client, StatusCode};
+    use serde_json::{json, Value};
+
+    use crate::helpers::stripe::{product, refunded_charge, send_event, unique_id};
+    use crate::helpers::token::{get_test_admin_token, get_test_user_token, SERVER_URL, TEST_USER_ID};
+
+    fn post(path: &str, token: &str, body: Value) -> (StatusCode, Value) {
+        let response = Client::new()
+            .post(format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&body)
+            .send()
+            .unwrap();
+        let status = response.status();
+
+        (status, response.json::<Value>().unwrap_or(Value::Null))
+    }
+
+    fn get(path: &str, token: &str) -> Value {
+        Client::new()
+            .get(format!("{}{}", SERVER_URL, path))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .unwrap()
+            .json::<Value>()
+            .unwrap()
+    }
+
+    // a product and a shipped order of the test user for two of it at 25.00, paid 55.00 with 5.00 shipping
+    fn create_shipped_order(payment_intent_id: &str) -> (String, String) {
+        create_order("shipped", payment_intent_id)
+    }
+
+    fn create_order(status: &str, payment_intent_id: &str) -> (String, String) {
+        let product_id = unique_id("prod_returns");
+        assert_eq!(send_event("product.created", product(&product_id, 10)), StatusCode::OK);
+
+        let order_id = unique_id("order_returns");
+        let (response_status, _) = post("/api/order/create", &get_test_admin_token(), json!({
+            "id": order_id,
+            "user_id": TEST_USER_ID,
+            "products": { &product_id: 2 },
+            "prices": { &product_id: "25.00" },
+            "status": status,
+            "name": "Returns test",
+            "amount_paid": "55.00",
+            "shipping_cost": "5.00",
+            "payment_intent_id": payment_intent_id,
+        }));
+        assert_eq!(response_status, StatusCode::OK);
+
+        (order_id, product_id)
+    }
+
+    fn clean_up(order_id: &str, product_id: &str) {
+        post(&format!("/api/order/delete/{}", order_id), &get_test_admin_token(), Value::Null);
+        send_event("product.deleted", product(product_id, 0));
+    }
+
+    // request, approve and receive one of the two products
+    fn received_return(order_id: &str, product_id: &str) -> String {
+        let admin = get_test_admin_token();
+        let (status, order_return) = post(
+            &format!("/api/order/returns/request/{}", order_id),
+            &get_test_user_token(),
+            json!({ "products": { product_id: 1 } }),
+        );
+        assert_eq!(status, StatusCode::CREATED);
+        let return_id = order_return["id"].as_str().unwrap().to_string();
+
+        assert_eq!(post(&format!("/api/order/returns/approve/{}", return_id), &admin, json!({})).0, StatusCode::OK);
+        assert_eq!(post(&format!("/api/order/returns/receive/{}", return_id), &admin, json!({})).0, StatusCode::OK);
+
+        return_id
+    }
+
+    #[test]
+    fn return_moves_through_its_statuses() {
+        let payment_intent_id = unique_id("pi_returns");
+        let (order_id, product_id) = create_shipped_order(&payment_intent_id);
+        let admin = get_test_admin_token();
+
+        let (status, order_return) = post(
+            &format!("/api/order/returns/request/{}", order_id),
+            &get_test_user_token(),
+            json!({ "products": { &product_id: 1 }, "reason": "too small" }),
+        );
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(order_return["status"], "requested");
+        let return_id = order_return["id"].as_str().unwrap().to_string();
+
+        // nothing is refunded before the goods are back
+        let (status, error) = post(&format!("/api/order/returns/refund/{}", return_id), &admin, json!({}));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "wrong_status");
+
+        let (status, order_return) = post(&format!("/api/order/returns/approve/{}", return_id), &admin, json!({}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(order_return["status"], "approved");
+
+        let (status, error) = post(&format!("/api/order/returns/approve/{}", return_id), &admin, json!({}));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "wrong_status");
+
+        let (status, order_return) = post(&format!("/api/order/returns/receive/{}", return_id), &admin, json!({}));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(order_return["status"], "received");
+
+        // one of the two products is worth half of the 50.00 paid for them
+        let (status, error) = post(&format!("/api/order/returns/refund/{}", return_id), &admin, json!({ "amount": "30.00" }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "amount_too_high");
+
+        let (status, error) = post(&format!("/api/order/returns/refund/{}", return_id), &admin, json!({ "amount": "0" }));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "invalid_amount");
+
+        assert_eq!(send_event("charge.refunded", refunded_charge(&payment_intent_id, &return_id, 2500)), StatusCode::OK);
+        let order_return = get(&format!("/api/order/returns/id/{}", return_id), &admin);
+        assert_eq!(order_return["status"], "refunded");
+
+        clean_up(&order_id, &product_id);
+    }
+
+    #[test]
+    fn refunded_return_is_not_refunded_twice() {
+        let payment_intent_id = unique_id("pi_returns");
+        let (order_id, product_id) = create_shipped_order(&payment_intent_id);
+        let return_id = received_return(&order_id, &product_id);
+        let admin = get_test_admin_token();
+
+        // stripe may deliver the same event more than once
+        send_event("charge.refunded", refunded_charge(&payment_intent_id, &return_id, 2500));
+        send_event("charge.refunded", refunded_charge(&payment_intent_id, &return_id, 2500));
+
+        let order = get(&format!("/api/order/id/{}", order_id), &admin);
+        assert_eq!(order["refunded_amount"], "25.00");
+
+        let (status, error) = post(&format!("/api/order/returns/refund/{}", return_id), &admin, json!({}));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "wrong_status");
+        assert_eq!(error["status"], "refunded");
+
+        // the other product is still returnable, the refunded one is not
+        let (status, error) = post(
+            &format!("/api/order/returns/request/{}", order_id),
+            &get_test_user_token(),
+            json!({ "products": { &product_id: 2 } }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "too_many");
+
+        clean_up(&order_id, &product_id);
+    }
+
+    #[test]
+    fn only_shipped_lines_are_returned() {
+        let (order_id, product_id) = create_order("processing", &unique_id("pi_returns"));
+
+        let (status, shipment) = post(
+            &format!("/api/order/ship/{}", order_id),
+            &get_test_admin_token(),
+            json!({ "carrier": "UPS", "products": { &product_id: 1 } }),
+        );
+        assert_eq!(status, StatusCode::CREATED, "{}", shipment);
+
+        // the second one is still in the warehouse
+        let (status, error) = post(
+            &format!("/api/order/returns/request/{}", order_id),
+            &get_test_user_token(),
+            json!({ "products": { &product_id: 2 } }),
+        );
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "too_many");
+        assert_eq!(error["returnable"], 1);
+
+        let (status, _) = post(
+            &format!("/api/order/returns/request/{}", order_id),
+            &get_test_user_token(),
+            json!({ "products": { &product_id: 1 } }),
+        );
+        assert_eq!(status, StatusCode::CREATED);
+
+        clean_up(&order_id, &product_id);
+    }
+}
\ No newline at end of file